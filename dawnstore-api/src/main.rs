@@ -1,10 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use axum::Router;
 use color_eyre::eyre;
 use dawnstore_core::{
     backends::postgres::PostgresBackend,
-    models::{Container, ForeignKey, ForeignKeyType},
+    models::{
+        Container, ForeignKey, ForeignKeyType, ForeignKind, KindOptions, NamespacePolicy,
+        SlotPlacement, SpecIndex, UniqueConstraint, ValidationRule,
+    },
 };
 use sqlx::PgPool;
 use tokio::net::TcpListener;
@@ -17,22 +20,46 @@ async fn main() -> eyre::Result<()> {
     let backend = PostgresBackend::new(pool);
 
     backend.sqlx_migrate().await?;
+    backend.seed_builtin_object_schemas().await?;
 
     backend
-        .seed_object_schema::<Container>(
+        .seed_object_schema_with_options::<Container>(
             "v2",
             "container",
             ["cont", "containers"],
-            [ForeignKey::new(
-                "parent",
-                Some("children"),
-                ForeignKeyType::OneOptional,
-                Some("container"),
-            )],
+            [
+                ForeignKey::new(
+                    "parent",
+                    Some("children"),
+                    ForeignKeyType::OneOptional,
+                    None::<String>,
+                )
+                .with_allowed_kinds([ForeignKind::new("container").with_api_version("v2")])
+                .with_namespace_policy(NamespacePolicy::SameNamespace)
+                .acyclic(),
+                ForeignKey::new(
+                    "slots[*].item",
+                    None::<String>,
+                    ForeignKeyType::OneOptional,
+                    Some("container"),
+                )
+                .with_edge_schema::<SlotPlacement>(),
+            ],
+            KindOptions::default()
+                .with_unique_constraint(
+                    UniqueConstraint::new("nr-per-parent", ["nr"]).per_foreign_key("parent"),
+                )
+                .with_index(SpecIndex::btree("nr"))
+                .with_rule(
+                    ValidationRule::new("!has(self.items) || self.items.size() <= 50")
+                        .with_message("a container holds at most 50 items"),
+                ),
         )
         .await?;
 
     let backend = Arc::new(backend);
+    backend.spawn_garbage_collector(Duration::from_secs(10));
+    backend.spawn_expiry_reaper(Duration::from_secs(10));
 
     let dawnstore_routes = dawnstore_core::controllers::get_dawnstore_default_routes(backend);
     let app = Router::new().merge(dawnstore_routes);
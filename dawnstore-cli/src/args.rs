@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -23,11 +23,94 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Display one or many resources
-    Get { resource: String },
+    Get {
+        resource: String,
+        /// only objects whose spec matches, e.g. owner=bob,tags@>red
+        #[arg(long)]
+        field_selector: Option<String>,
+        /// only objects matching the query, e.g. 'spec.nr > 2 and ref(parent).name = shelf1'
+        #[arg(short, long)]
+        query: Option<String>,
+    },
+    /// Find objects by the words in their names, labels, annotations and specs
+    Search {
+        /// e.g. reis, "old screws" or -basmati
+        query: String,
+        /// only objects of this kind
+        #[arg(short, long)]
+        kind: Option<String>,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
     /// Delete resources
     Delete { resource: String, item_name: String },
+    /// Show a resource together with its recent events
+    Describe { resource: String, item_name: String },
     /// Edit resource
     Edit { resource: String, item_name: String },
+    /// Show a resource and the objects below it as a tree
+    Tree {
+        resource: String,
+        item_name: String,
+        /// show the objects above it instead, e.g. the full location
+        #[arg(long)]
+        up: bool,
+        /// key path of the followed foreign key, every key if not set
+        #[arg(short = 'k', long)]
+        foreign_key: Option<String>,
+        #[arg(short, long)]
+        depth: Option<u32>,
+    },
     /// Apply resource from file
-    Apply { path: String },
+    Apply {
+        path: String,
+        /// merge the manifest into the stored objects and track field ownership
+        #[arg(long)]
+        server_side: bool,
+        #[arg(long, default_value = "dawnstore-cli")]
+        field_manager: String,
+        /// take over fields owned by other field managers
+        #[arg(long)]
+        force_conflicts: bool,
+        /// delete objects matched by the selector or applied set which are not in the file
+        #[arg(long)]
+        prune: bool,
+        /// label selector of the prune, e.g. set=kitchen
+        #[arg(short = 'l', long)]
+        selector: Option<String>,
+        /// label all applied objects as members of this set
+        #[arg(long)]
+        applied_set: Option<String>,
+    },
+    /// Maintenance of the store
+    Admin {
+        #[command(subcommand)]
+        command: AdminCommands,
+    },
+    /// Patch labels, annotations or spec of a resource
+    Patch {
+        resource: String,
+        item_name: String,
+        /// patch document as json or yaml, e.g. '{"spec": {"nr": 2}}'
+        #[arg(short, long)]
+        patch: String,
+        #[arg(long = "type", value_enum, default_value_t = PatchTypeArg::Merge)]
+        patch_type: PatchTypeArg,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AdminCommands {
+    /// Check the objects against their relations and kinds
+    Fsck {
+        /// fix relations and string ids, specs are never changed
+        #[arg(long)]
+        repair: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PatchTypeArg {
+    Merge,
+    Json,
 }
@@ -2,6 +2,7 @@ use std::io::Write;
 
 use clap::Parser;
 use color_eyre::eyre::{OptionExt, bail};
+use dawnstore_client_lib::DawnstoreApiError;
 use dawnstore_lib::*;
 use serde_json::Value;
 use tempfile::NamedTempFile;
@@ -17,8 +18,21 @@ async fn main() -> color_eyre::Result<()> {
     let context = serde_yml::from_str::<config::Context>(&file)?;
     let api = dawnstore_client_lib::Api::new(&context.url);
 
+    let result = run(&args, &api).await;
+    // errors of the api are listed per object instead of the report
+    if let Err(e) = &result
+        && let Some(DawnstoreApiError::ApiError(_, error)) = e.downcast_ref::<DawnstoreApiError>()
+        && !error.errors.is_empty()
+    {
+        utils::print_error_response(error);
+        std::process::exit(1);
+    }
+    result
+}
+
+async fn run(args: &args::Cli, api: &dawnstore_client_lib::Api) -> color_eyre::Result<()> {
     match &args.command {
-        args::Commands::Get { resource }
+        args::Commands::Get { resource, .. }
             if resource == "resource-definitions" || resource == "rd" =>
         {
             let rd = api.get_resource_definitions(&Default::default()).await?;
@@ -33,7 +47,15 @@ async fn main() -> color_eyre::Result<()> {
                 );
             }
         }
-        args::Commands::Get { resource } => {
+        args::Commands::Get {
+            resource,
+            field_selector,
+            query,
+        } => {
+            let field_selectors = field_selector
+                .as_deref()
+                .map(|x| parse_field_selector(x).ok_or_eyre("invalid field selector"))
+                .transpose()?;
             let filter = GetObjectsFilter {
                 namespace: if args.all_namespaces {
                     None
@@ -46,7 +68,10 @@ async fn main() -> color_eyre::Result<()> {
                     Some(resource.clone())
                 },
                 name: None,
+                ids: None,
                 page: None,
+                field_selectors,
+                query: query.clone(),
                 page_size: None,
                 fill_child_foreign_keys: false,
                 fill_parent_foreign_keys: false,
@@ -66,10 +91,116 @@ async fn main() -> color_eyre::Result<()> {
                 );
             }
         }
+        args::Commands::Search { query, kind, limit } => {
+            let filter = SearchFilter {
+                query: query.clone(),
+                namespace: if args.all_namespaces {
+                    None
+                } else {
+                    Some(args.namespace.as_deref().unwrap_or("default").to_string())
+                },
+                kind: kind.clone(),
+                limit: *limit,
+            };
+            let result = api.search(&filter).await?;
+            println!(
+                "{:20} {:20} {:30} {:8}",
+                "Namespace:", "Kind:", "Name:", "Rank:"
+            );
+            println!(
+                "----------------------------------------------------------------------------------"
+            );
+            for x in result.hits {
+                println!(
+                    "{:20} {:20} {:30} {:<8.3}",
+                    x.namespace, x.kind, x.name, x.rank
+                );
+                // the matches in bold
+                let headline = x
+                    .headline
+                    .replace("<b>", "\x1b[1m")
+                    .replace("</b>", "\x1b[0m");
+                println!("    {headline}");
+            }
+        }
         args::Commands::Delete {
             resource: _,
             item_name: _,
         } => todo!(),
+        args::Commands::Tree {
+            resource,
+            item_name,
+            up,
+            foreign_key,
+            depth,
+        } => {
+            let result = api
+                .traverse(&TraverseFilter {
+                    namespace: Some(args.namespace.as_deref().unwrap_or("default").to_string()),
+                    kind: resource.clone(),
+                    name: item_name.clone(),
+                    direction: if *up {
+                        TraverseDirection::Ancestors
+                    } else {
+                        TraverseDirection::Descendants
+                    },
+                    foreign_key: foreign_key.clone(),
+                    depth: *depth,
+                })
+                .await?;
+            utils::print_tree(&result, *up, foreign_key.is_none());
+        }
+        args::Commands::Describe {
+            resource,
+            item_name,
+        } => {
+            let namespace = args.namespace.as_deref().unwrap_or("default").to_string();
+            let filter = GetObjectsFilter {
+                namespace: Some(namespace.clone()),
+                kind: Some(resource.clone()),
+                name: Some(item_name.clone()),
+                ids: None,
+                page: None,
+                field_selectors: None,
+                query: None,
+                page_size: None,
+                fill_child_foreign_keys: false,
+                fill_parent_foreign_keys: false,
+            };
+            let mut rd = api.get_objects(&filter).await?;
+            let Some(obj) = rd.pop() else {
+                bail!("object not found");
+            };
+            print!("{}", serde_yml::to_string(&obj)?);
+            let events = api
+                .get_events(&GetEventsFilter {
+                    namespace: Some(namespace),
+                    kind: obj.kind.clone(),
+                    name: obj.name.clone(),
+                    limit: None,
+                })
+                .await?;
+            println!();
+            println!("Events:");
+            if events.is_empty() {
+                println!("  <none>");
+            } else {
+                println!(
+                    "  {:10} {:20} {:8} {:30} Message:",
+                    "Type:", "Reason:", "Count:", "Last Seen:"
+                );
+                for e in events {
+                    println!(
+                        "  {:10} {:20} {:<8} {:30} {}",
+                        format!("{:?}", e.spec.event_type),
+                        e.spec.reason,
+                        e.spec.count,
+                        e.spec.last_seen.to_string(),
+                        e.spec.message
+                    );
+                }
+            }
+        }
         args::Commands::Edit {
             resource,
             item_name,
@@ -78,7 +209,10 @@ async fn main() -> color_eyre::Result<()> {
                 namespace: Some(args.namespace.as_deref().unwrap_or("default").to_string()),
                 kind: Some(resource.clone()),
                 name: Some(item_name.clone()),
+                ids: None,
                 page: None,
+                field_selectors: None,
+                query: None,
                 page_size: None,
                 fill_child_foreign_keys: true,
                 fill_parent_foreign_keys: true,
@@ -118,6 +252,12 @@ async fn main() -> color_eyre::Result<()> {
                         )])),
                     );
                 });
+                // the status and managed fields are shown but ignored by apply
+                props.insert("status".to_string(), Value::Object(Default::default()));
+                props.insert(
+                    "managed_fields".to_string(),
+                    Value::Object(Default::default()),
+                );
             }
             let str_json_schema = serde_json::to_string(&json_schema_value)?;
             let mut file = NamedTempFile::with_suffix(".json")?;
@@ -136,11 +276,74 @@ async fn main() -> color_eyre::Result<()> {
             let json_file = serde_json::to_string(&value)?;
             api.apply_str(json_file).await?;
         }
-        args::Commands::Apply { path } => {
+        args::Commands::Patch {
+            resource,
+            item_name,
+            patch,
+            patch_type,
+        } => {
+            let req = PatchObject {
+                namespace: Some(args.namespace.as_deref().unwrap_or("default").to_string()),
+                kind: resource.clone(),
+                name: item_name.clone(),
+                patch_type: match patch_type {
+                    args::PatchTypeArg::Merge => PatchType::Merge,
+                    args::PatchTypeArg::Json => PatchType::Json,
+                },
+                patch: serde_yml::from_str::<serde_json::Value>(patch)?,
+            };
+            let obj = api.patch(&req).await?;
+            println!("{} patched", obj.name);
+        }
+        args::Commands::Admin {
+            command: args::AdminCommands::Fsck { repair },
+        } => {
+            let report = api.fsck(&FsckOptions { repair: *repair }).await?;
+            if !report.issues.is_empty() {
+                println!(
+                    "{:28} {:40} {:10} Message:",
+                    "Issue:", "Object:", "Repaired:"
+                );
+                for i in &report.issues {
+                    println!(
+                        "{:28} {:40} {:10} {}",
+                        format!("{:?}", i.kind),
+                        i.object.as_deref().unwrap_or("-"),
+                        i.repaired,
+                        i.message
+                    );
+                }
+                println!();
+            }
+            let repaired = report.issues.iter().filter(|x| x.repaired).count();
+            println!(
+                "{} objects scanned, {} issues found, {} repaired",
+                report.scanned_objects,
+                report.issues.len(),
+                repaired
+            );
+        }
+        args::Commands::Apply {
+            path,
+            server_side,
+            field_manager,
+            force_conflicts,
+            prune,
+            selector,
+            applied_set,
+        } => {
             let file = std::fs::read_to_string(path)?;
             let value = serde_yml::from_str::<serde_json::Value>(&file)?;
             let json_file = serde_json::to_string(&value)?;
-            api.apply_str(json_file)
+            let options = ApplyOptions {
+                server_side: *server_side,
+                field_manager: Some(field_manager.clone()),
+                force_conflicts: *force_conflicts,
+                prune: *prune,
+                selector: selector.clone(),
+                applied_set: applied_set.clone(),
+            };
+            api.apply_str_with_options(json_file, &options)
                 .await?
                 .iter()
                 .for_each(|x| println!("{}", x.name));
@@ -42,3 +42,87 @@ pub fn edit_with_default_editor<'r, S: Into<&'r str>>(
 
     Ok(Some(after_text))
 }
+
+/// prints the errors of a failed request grouped by the object they belong to
+pub fn print_error_response(error: &dawnstore_lib::ErrorResponse) {
+    eprintln!("Error: {}", error.code);
+    let mut objects = Vec::<Option<&str>>::new();
+    for e in &error.errors {
+        if !objects.contains(&e.object.as_deref()) {
+            objects.push(e.object.as_deref());
+        }
+    }
+    for object in objects {
+        eprintln!("{}:", object.unwrap_or("request"));
+        for e in error
+            .errors
+            .iter()
+            .filter(|x| x.object.as_deref() == object)
+        {
+            match e.pointer.as_deref().filter(|x| !x.is_empty()) {
+                Some(pointer) => eprintln!("  {} [{}] {}", pointer, e.code, e.message),
+                None => eprintln!("  [{}] {}", e.code, e.message),
+            }
+        }
+    }
+}
+
+/// prints the start object of a traversal and the reached objects below it,
+/// with `up` the referenced objects are the children of a node
+pub fn print_tree(result: &dawnstore_lib::TraverseResult, up: bool, show_keys: bool) {
+    let Some(root) = result.objects.first() else {
+        return;
+    };
+    let root_id = format!("{}/{}/{}", root.namespace, root.kind, root.name);
+    // objects of other namespaces keep their namespace
+    let label = |string_id: &str| match string_id.strip_prefix(&format!("{}/", root.namespace)) {
+        Some(x) => x.to_owned(),
+        None => string_id.to_owned(),
+    };
+
+    fn walk<'a>(
+        node: &'a str,
+        prefix: &str,
+        path: &mut Vec<&'a str>,
+        result: &'a dawnstore_lib::TraverseResult,
+        up: bool,
+        show_keys: bool,
+        label: &dyn Fn(&str) -> String,
+    ) {
+        let mut children = result
+            .edges
+            .iter()
+            .filter_map(|x| match up {
+                true => (x.object == node).then_some((x.foreign_object.as_str(), x)),
+                false => (x.foreign_object == node).then_some((x.object.as_str(), x)),
+            })
+            .collect::<Vec<_>>();
+        children.sort_by_key(|(id, _)| label(id));
+        let count = children.len();
+        for (i, (child, edge)) in children.into_iter().enumerate() {
+            let last = i + 1 == count;
+            let key = match show_keys {
+                true => format!(" ({})", edge.foreign_key),
+                false => String::new(),
+            };
+            // a cycle is shown once, the traversal stops at its depth anyway
+            let cycle = match path.contains(&child) {
+                true => " (cycle)",
+                false => "",
+            };
+            let branch = if last { "└── " } else { "├── " };
+            println!("{prefix}{branch}{}{key}{cycle}", label(child));
+            if !cycle.is_empty() {
+                continue;
+            }
+            let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            path.push(child);
+            walk(child, &prefix, path, result, up, show_keys, label);
+            path.pop();
+        }
+    }
+
+    println!("{}", label(&root_id));
+    let mut path = vec![root_id.as_str()];
+    walk(&root_id, "", &mut path, result, up, show_keys, &label);
+}
@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+
+use crate::{Api, DawnstoreApiError, LeaseRequest};
+
+/// whole seconds, rounded up so a short lease is not expired right away
+fn lease_seconds(lease_duration: Duration) -> i64 {
+    lease_duration.as_secs_f64().ceil() as i64
+}
+
+/// elects a single active instance among all instances using the same lease
+pub struct LeaderElection<'a> {
+    api: &'a Api,
+    request: LeaseRequest,
+    lease_duration: Duration,
+    retry_period: Duration,
+}
+
+impl<'a> LeaderElection<'a> {
+    pub fn new(api: &'a Api, lease_name: impl Into<String>, identity: impl Into<String>) -> Self {
+        let lease_duration = Duration::from_secs(15);
+        Self {
+            api,
+            request: LeaseRequest {
+                namespace: None,
+                name: lease_name.into(),
+                holder_identity: identity.into(),
+                lease_duration_seconds: Some(lease_seconds(lease_duration)),
+            },
+            lease_duration,
+            retry_period: Duration::from_secs(2),
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.request.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn with_lease_duration(mut self, lease_duration: Duration) -> Self {
+        self.lease_duration = lease_duration;
+        self.request.lease_duration_seconds = Some(lease_seconds(lease_duration));
+        self
+    }
+
+    /// how often acquiring is retried and the held lease is renewed,
+    /// has to be well below the lease duration
+    pub fn with_retry_period(mut self, retry_period: Duration) -> Self {
+        self.retry_period = retry_period;
+        self
+    }
+
+    /// waits until the lease is acquired
+    pub async fn acquire(&self) -> Result<(), DawnstoreApiError> {
+        loop {
+            match self.api.acquire_lease(&self.request).await {
+                Ok(_) => return Ok(()),
+                Err(DawnstoreApiError::ApiError(StatusCode::CONFLICT, _)) => {
+                    tokio::time::sleep(self.retry_period).await
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// waits for the leadership and runs `leading` while renewing the lease,
+    /// `leading` is dropped as soon as the lease could not be renewed in time
+    pub async fn run<F: Future>(&self, leading: F) -> Result<F::Output, DawnstoreApiError> {
+        self.acquire().await?;
+        let mut last_renew = Instant::now();
+        let mut ticker = tokio::time::interval(self.retry_period);
+        ticker.tick().await;
+        tokio::pin!(leading);
+        let output = loop {
+            tokio::select! {
+                output = &mut leading => break output,
+                _ = ticker.tick() => {
+                    match self.api.renew_lease(&self.request).await {
+                        Ok(_) => last_renew = Instant::now(),
+                        Err(DawnstoreApiError::ApiError(StatusCode::CONFLICT, _)) => {
+                            return Err(DawnstoreApiError::LeadershipLost(self.request.name.clone()));
+                        }
+                        // transient errors are fine as long as the lease did not run out
+                        Err(_) if last_renew.elapsed() < self.lease_duration => {}
+                        Err(_) => {
+                            return Err(DawnstoreApiError::LeadershipLost(self.request.name.clone()));
+                        }
+                    }
+                }
+            }
+        };
+        self.api.release_lease(&self.request).await?;
+        Ok(output)
+    }
+}
@@ -1,13 +1,30 @@
 pub use dawnstore_lib::*;
-use reqwest::{Client, IntoUrl};
+use reqwest::Client;
 use serde::{Serialize, de::DeserializeOwned};
 
+pub mod leader_election;
+
 #[derive(thiserror::Error, Debug)]
 pub enum DawnstoreApiError {
     #[error("Error from reqwest: {0}")]
     RequestError(#[from] reqwest::Error),
     #[error("Error from api code: {0} msg: {1}")]
-    ApiError(reqwest::StatusCode, String),
+    ApiError(reqwest::StatusCode, ErrorResponse),
+    #[error("Leadership of lease {0} lost")]
+    LeadershipLost(String),
+}
+
+/// reads the error body of a failed request, bodies which are not an
+/// ErrorResponse end up as its message
+async fn api_error(response: reqwest::Response) -> Result<DawnstoreApiError, reqwest::Error> {
+    let status = response.status();
+    let body = response.text().await?;
+    let error = serde_json::from_str::<ErrorResponse>(&body).unwrap_or(ErrorResponse {
+        code: status.to_string(),
+        message: body,
+        errors: Vec::new(),
+    });
+    Ok(DawnstoreApiError::ApiError(status, error))
 }
 
 pub struct Api {
@@ -48,7 +65,7 @@ impl Api {
         if i.status().is_success() {
             Ok(i.json::<Vec<ResourceDefinition>>().await?)
         } else {
-            Err(DawnstoreApiError::ApiError(i.status(), i.text().await?))
+            Err(api_error(i).await?)
         }
     }
 
@@ -65,7 +82,7 @@ impl Api {
         if i.status().is_success() {
             Ok(i.json::<Vec<ReturnObject<serde_json::Value>>>().await?)
         } else {
-            Err(DawnstoreApiError::ApiError(i.status(), i.text().await?))
+            Err(api_error(i).await?)
         }
     }
 
@@ -82,7 +99,24 @@ impl Api {
         if i.status().is_success() {
             Ok(i.json::<Vec<ReturnObject<T>>>().await?)
         } else {
-            Err(DawnstoreApiError::ApiError(i.status(), i.text().await?))
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn traverse(
+        &self,
+        filter: &TraverseFilter,
+    ) -> Result<TraverseResult, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/traverse", self.base_url))
+            .json(filter)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<TraverseResult>().await?)
+        } else {
+            Err(api_error(i).await?)
         }
     }
 
@@ -99,17 +133,41 @@ impl Api {
         if i.status().is_success() {
             Ok(i.json::<ObjectInfos>().await?)
         } else {
-            Err(DawnstoreApiError::ApiError(i.status(), i.text().await?))
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn search(&self, filter: &SearchFilter) -> Result<SearchResults, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/search", self.base_url))
+            .json(filter)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<SearchResults>().await?)
+        } else {
+            Err(api_error(i).await?)
         }
     }
 
     pub async fn apply_str(
         &self,
         content: String,
+    ) -> Result<Vec<ReturnObject<serde_json::Value>>, DawnstoreApiError> {
+        self.apply_str_with_options(content, &ApplyOptions::default())
+            .await
+    }
+
+    pub async fn apply_str_with_options(
+        &self,
+        content: String,
+        options: &ApplyOptions,
     ) -> Result<Vec<ReturnObject<serde_json::Value>>, DawnstoreApiError> {
         let i = self
             .client
             .post(format!("{}/apply", self.base_url))
+            .query(options)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .body(content)
             .send()
@@ -117,7 +175,41 @@ impl Api {
         if i.status().is_success() {
             Ok(i.json::<Vec<ReturnObject<serde_json::Value>>>().await?)
         } else {
-            Err(DawnstoreApiError::ApiError(i.status(), i.text().await?))
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn patch(
+        &self,
+        req: &PatchObject,
+    ) -> Result<ReturnObject<serde_json::Value>, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/patch", self.base_url))
+            .json(req)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<ReturnObject<serde_json::Value>>().await?)
+        } else {
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn transaction(
+        &self,
+        req: &Transaction,
+    ) -> Result<Vec<TransactionResult>, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/transaction", self.base_url))
+            .json(req)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<Vec<TransactionResult>>().await?)
+        } else {
+            Err(api_error(i).await?)
         }
     }
 
@@ -131,7 +223,125 @@ impl Api {
         if i.status().is_success() {
             Ok(())
         } else {
-            Err(DawnstoreApiError::ApiError(i.status(), i.text().await?))
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn remove_finalizer(&self, req: &RemoveFinalizer) -> Result<(), DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/remove-finalizer", self.base_url))
+            .json(req)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(())
+        } else {
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn acquire_lease(
+        &self,
+        req: &LeaseRequest,
+    ) -> Result<ReturnObject<Lease>, DawnstoreApiError> {
+        self.lease_exchange("acquire-lease", req).await
+    }
+
+    pub async fn renew_lease(
+        &self,
+        req: &LeaseRequest,
+    ) -> Result<ReturnObject<Lease>, DawnstoreApiError> {
+        self.lease_exchange("renew-lease", req).await
+    }
+
+    pub async fn release_lease(
+        &self,
+        req: &LeaseRequest,
+    ) -> Result<ReturnObject<Lease>, DawnstoreApiError> {
+        self.lease_exchange("release-lease", req).await
+    }
+
+    async fn lease_exchange(
+        &self,
+        path: &str,
+        req: &LeaseRequest,
+    ) -> Result<ReturnObject<Lease>, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/{}", self.base_url, path))
+            .json(req)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<ReturnObject<Lease>>().await?)
+        } else {
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn update_status(
+        &self,
+        req: &UpdateObjectStatus,
+    ) -> Result<ReturnObject<serde_json::Value>, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/update-status", self.base_url))
+            .json(req)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<ReturnObject<serde_json::Value>>().await?)
+        } else {
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn record_event(
+        &self,
+        req: &RecordEvent,
+    ) -> Result<ReturnObject<Event>, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/record-event", self.base_url))
+            .json(req)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<ReturnObject<Event>>().await?)
+        } else {
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn get_events(
+        &self,
+        filter: &GetEventsFilter,
+    ) -> Result<Vec<ReturnObject<Event>>, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/get-events", self.base_url))
+            .json(filter)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<Vec<ReturnObject<Event>>>().await?)
+        } else {
+            Err(api_error(i).await?)
+        }
+    }
+
+    pub async fn fsck(&self, options: &FsckOptions) -> Result<FsckReport, DawnstoreApiError> {
+        let i = self
+            .client
+            .post(format!("{}/admin/fsck", self.base_url))
+            .json(options)
+            .send()
+            .await?;
+        if i.status().is_success() {
+            Ok(i.json::<FsckReport>().await?)
+        } else {
+            Err(api_error(i).await?)
         }
     }
 
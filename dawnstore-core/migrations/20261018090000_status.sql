@@ -0,0 +1,4 @@
+-- status subresource, written independently of the spec
+ALTER TABLE object_schemas ADD COLUMN status_json_schema TEXT;
+
+ALTER TABLE objects ADD COLUMN status JSONB;
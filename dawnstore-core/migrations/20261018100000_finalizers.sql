@@ -0,0 +1,3 @@
+-- finalizers block the deletion of an object until they are removed
+ALTER TABLE objects ADD COLUMN finalizers TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE objects ADD COLUMN deletion_timestamp TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+-- owner references, dependents are garbage collected once their owners are gone
+ALTER TABLE objects ADD COLUMN owners JSONB NOT NULL DEFAULT '[]'::jsonb;
+
+CREATE INDEX idx_objects_owners ON objects USING GIN (owners jsonb_path_ops);
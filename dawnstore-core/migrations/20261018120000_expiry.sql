@@ -0,0 +1,6 @@
+-- expiring objects, either explicit or through the ttl of their kind
+ALTER TABLE object_schemas ADD COLUMN ttl_seconds BIGINT;
+
+ALTER TABLE objects ADD COLUMN expires_at TIMESTAMPTZ;
+
+CREATE INDEX idx_objects_expires_at ON objects (expires_at) WHERE expires_at IS NOT NULL;
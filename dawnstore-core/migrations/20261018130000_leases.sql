@@ -0,0 +1,3 @@
+-- leases insert with ON CONFLICT (string_id), so it has to be unique
+DROP INDEX idx_objects_string_id_lookup;
+CREATE UNIQUE INDEX idx_objects_string_id_lookup ON objects (string_id);
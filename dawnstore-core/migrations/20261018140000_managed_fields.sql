@@ -0,0 +1,2 @@
+-- field managers of server-side apply, manager name -> owned json pointers
+ALTER TABLE objects ADD COLUMN managed_fields JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,13 @@
+-- resource version for optimistic concurrency, bumped on every write
+ALTER TABLE objects ADD COLUMN resource_version BIGINT NOT NULL DEFAULT 1;
+
+CREATE FUNCTION bump_resource_version() RETURNS trigger AS $$
+BEGIN
+    NEW.resource_version := OLD.resource_version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER objects_bump_resource_version
+    BEFORE UPDATE ON objects
+    FOR EACH ROW EXECUTE FUNCTION bump_resource_version();
@@ -0,0 +1,33 @@
+-- unique constraints over spec paths, declared when a kind is seeded
+CREATE TYPE unique_constraint_scope AS ENUM (
+    'Global',
+    'Namespace',
+    'ForeignKey'
+);
+
+CREATE TABLE unique_constraints (
+    id UUID PRIMARY KEY,
+    api_version TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    paths TEXT[] NOT NULL,
+    scope unique_constraint_scope NOT NULL,
+    -- key path of the foreign key the constraint is scoped to
+    foreign_key_path TEXT
+);
+
+CREATE UNIQUE INDEX idx_unique_constraints_lookup ON unique_constraints (api_version, kind, name);
+
+-- the values of unique constraints scoped to a foreign key, the relations live
+-- in another table, so an index on objects can not cover them
+CREATE TABLE unique_foreign_key_values (
+    constraint_id UUID NOT NULL REFERENCES unique_constraints (id) ON DELETE CASCADE,
+    -- the referenced object the values are unique for
+    foreign_object_id UUID NOT NULL REFERENCES objects (id) ON DELETE CASCADE,
+    object_id UUID NOT NULL REFERENCES objects (id) ON DELETE CASCADE,
+    -- the text of the values like `spec #>> path` of the other scopes
+    key TEXT[] NOT NULL,
+    PRIMARY KEY (constraint_id, foreign_object_id, key)
+);
+
+CREATE INDEX idx_unique_foreign_key_values_object_id ON unique_foreign_key_values (object_id);
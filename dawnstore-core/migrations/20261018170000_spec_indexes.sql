@@ -0,0 +1,15 @@
+-- expression indexes over spec paths, declared when a kind is seeded
+CREATE TYPE spec_index_type AS ENUM (
+    'BTree',
+    'Gin'
+);
+
+CREATE TABLE spec_indexes (
+    id UUID PRIMARY KEY,
+    api_version TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    path TEXT NOT NULL,
+    type spec_index_type NOT NULL
+);
+
+CREATE UNIQUE INDEX idx_spec_indexes_lookup ON spec_indexes (api_version, kind, path, type);
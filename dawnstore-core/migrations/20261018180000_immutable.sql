@@ -0,0 +1,2 @@
+-- objects of immutable kinds can not change their spec after creation
+ALTER TABLE object_schemas ADD COLUMN immutable BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+-- expression based validation rules of a kind, see ValidationRule
+ALTER TABLE object_schemas ADD COLUMN validation_rules JSONB NOT NULL DEFAULT '[]';
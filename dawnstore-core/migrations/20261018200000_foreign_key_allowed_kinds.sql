@@ -0,0 +1,7 @@
+ALTER TABLE foreign_key_constraints ADD COLUMN allowed_kinds JSONB NOT NULL DEFAULT '[]';
+
+UPDATE foreign_key_constraints
+SET allowed_kinds = jsonb_build_array(jsonb_build_object('kind', foreign_key_kind))
+WHERE foreign_key_kind IS NOT NULL;
+
+ALTER TABLE foreign_key_constraints DROP COLUMN foreign_key_kind;
@@ -0,0 +1 @@
+ALTER TABLE foreign_key_constraints ADD COLUMN acyclic BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+ALTER TABLE foreign_key_constraints ADD COLUMN edge_json_schema TEXT;
+
+ALTER TABLE relations ADD COLUMN properties JSONB;
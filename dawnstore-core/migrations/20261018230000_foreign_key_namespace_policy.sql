@@ -0,0 +1 @@
+ALTER TABLE foreign_key_constraints ADD COLUMN namespace_policy JSONB NOT NULL DEFAULT '"Any"';
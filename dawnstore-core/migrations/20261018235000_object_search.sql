@@ -0,0 +1,10 @@
+-- names rank above labels and annotations, the strings of the spec last.
+-- the simple configuration does not stem, names and notes are in any language
+ALTER TABLE objects ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple'::regconfig, name), 'A') ||
+    setweight(jsonb_to_tsvector('simple'::regconfig, labels, '["string"]'), 'B') ||
+    setweight(jsonb_to_tsvector('simple'::regconfig, annotations, '["string"]'), 'B') ||
+    setweight(jsonb_to_tsvector('simple'::regconfig, spec, '["string"]'), 'C')
+) STORED;
+
+CREATE INDEX idx_objects_search_vector ON objects USING GIN (search_vector);
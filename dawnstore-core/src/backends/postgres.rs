@@ -1,26 +1,116 @@
 use std::collections::{HashMap, HashSet};
 
 use serde_json::Value;
-use sqlx::{PgPool, Pool, Postgres, migrate::MigrateError};
+use sqlx::{PgConnection, PgPool, Pool, Postgres, migrate::MigrateError};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
-    backends::postgres::data_models::{ForeignKeyConstraint, ObjectInfo, ObjectSchema, Relation},
-    error::DawnStoreError,
-    models::ForeignKey,
+    backends::postgres::{
+        apply_impl::KindSchema,
+        data_models::{ForeignKeyConstraint, Object, ObjectInfo, ObjectSchema, Relation},
+    },
+    error::{DawnStoreError, ObjectError},
+    models::{ForeignKey, KindOptions},
 };
 
 use dawnstore_lib::*;
 
 mod apply_impl;
 mod data_models;
+mod delete_impl;
+mod events;
+mod fsck;
+mod garbage_collector;
+mod graph;
+mod index_impl;
+mod key_path;
+mod lease;
+mod patch_impl;
 mod queries;
+mod query_impl;
+mod reaper;
+mod transaction;
+mod unique_impl;
+
+/// keeps a validation error to report it together with the errors of the
+/// other checks and objects, every other error aborts the request
+fn collect_validation_error<T>(
+    errors: &mut Vec<DawnStoreError>,
+    result: Result<T, DawnStoreError>,
+) -> Result<Option<T>, DawnStoreError> {
+    match result {
+        Ok(x) => Ok(Some(x)),
+        Err(DawnStoreError::ValidationFailed(x)) => {
+            errors.extend(x.into_iter().map(|x| x.error));
+            Ok(None)
+        }
+        Err(e) if e.is_validation_error() => {
+            errors.push(e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
 
 pub struct PostgresBackend {
     pool: Pool<Postgres>,
     foreign_key_cache: RwLock<HashMap<String, Vec<ForeignKeyConstraint>>>,
-    schema_cache: RwLock<HashMap<String, jsonschema::Validator>>,
+    schema_cache: RwLock<HashMap<String, KindSchema>>,
+}
+
+/// only recorded after the commit, a rolled back prune did not happen
+fn pruned_events(pruned: &[Object]) -> impl Iterator<Item = RecordEvent> + '_ {
+    pruned.iter().map(|obj| RecordEvent {
+        involved_object: obj.reference(),
+        event_type: EventType::Normal,
+        reason: "Pruned".to_string(),
+        message: "not part of the applied objects anymore".to_string(),
+    })
+}
+
+/// a warning for every object of the request the error rejects, objects the
+/// error does not name are rejected together with the whole request
+fn validation_failed_events(
+    references: &HashMap<String, ObjectReference>,
+    error: &DawnStoreError,
+) -> Vec<RecordEvent> {
+    let messages = match error {
+        DawnStoreError::ValidationFailed(x) => x
+            .iter()
+            .map(|x| (x.object.as_str(), x.error.to_string()))
+            .collect::<Vec<_>>(),
+        DawnStoreError::ForeignKeyCycle { cycle, .. } => cycle
+            .iter()
+            .map(|x| (x.as_str(), error.to_string()))
+            .collect(),
+        DawnStoreError::UniqueConstraintViolation { object, .. } => {
+            vec![(object.as_str(), error.to_string())]
+        }
+        e if e.is_validation_error() => references
+            .keys()
+            .map(|x| (x.as_str(), e.to_string()))
+            .collect(),
+        _ => return vec![],
+    };
+    let mut per_object = HashMap::<&str, Vec<String>>::new();
+    for (string_id, message) in messages {
+        let object_messages = per_object.entry(string_id).or_default();
+        if !object_messages.contains(&message) {
+            object_messages.push(message);
+        }
+    }
+    per_object
+        .into_iter()
+        .filter_map(|(string_id, messages)| {
+            Some(RecordEvent {
+                involved_object: references.get(string_id)?.clone(),
+                event_type: EventType::Warning,
+                reason: "ValidationFailed".to_string(),
+                message: messages.join("; "),
+            })
+        })
+        .collect()
 }
 
 impl PostgresBackend {
@@ -50,60 +140,254 @@ impl PostgresBackend {
         kind: impl Into<String>,
         aliases: impl IntoIterator<Item = impl Into<String>>,
         foreign_keys: impl IntoIterator<Item = ForeignKey>,
+    ) -> Result<(), DawnStoreError> {
+        self.seed_object_schema_with_options::<T>(
+            api_version,
+            kind,
+            aliases,
+            foreign_keys,
+            KindOptions::default(),
+        )
+        .await
+    }
+
+    pub async fn seed_object_schema_with_options<T: schemars::JsonSchema>(
+        &self,
+        api_version: impl Into<String>,
+        kind: impl Into<String>,
+        aliases: impl IntoIterator<Item = impl Into<String>>,
+        foreign_keys: impl IntoIterator<Item = ForeignKey>,
+        options: KindOptions,
     ) -> Result<(), DawnStoreError> {
         let api_version = api_version.into();
         let kind = kind.into();
         let mut trans = self.pool.begin().await?;
-        let obj = queries::get_object_schema(trans.as_mut(), &api_version, &kind).await?;
-        if obj.is_some() {
-            return Ok(());
-        }
-        let schema = schemars::schema_for!(T);
-        let schema = serde_json::to_string(&schema)?;
-        queries::insert_object_schema(
+        // the definition in code wins, a changed kind is updated on the next start
+        self.upsert_object_schema::<T>(
             trans.as_mut(),
-            &ObjectSchema {
-                id: Uuid::new_v4(),
-                api_version: api_version.clone(),
-                kind: kind.clone(),
-                json_schema: schema,
-                aliases: aliases.into_iter().map(|x| x.into()).collect(),
-            },
+            &api_version,
+            &kind,
+            aliases,
+            foreign_keys,
+            &options,
         )
         .await?;
+        unique_impl::sync_unique_constraints(
+            trans.as_mut(),
+            &api_version,
+            &kind,
+            options.unique_constraints.as_slice(),
+        )
+        .await?;
+        index_impl::sync_spec_indexes(
+            trans.as_mut(),
+            &api_version,
+            &kind,
+            options.indexes.as_slice(),
+        )
+        .await?;
+        trans.commit().await?;
+        let type_id = format!("{api_version}/{kind}");
+        self.schema_cache.write().await.remove(&type_id);
+        self.foreign_key_cache.write().await.remove(&type_id);
+
+        Ok(())
+    }
+
+    /// foreign keys are matched by their key path, so existing relations keep
+    /// their key. keys which are not declared anymore are removed with their relations
+    async fn upsert_object_schema<T: schemars::JsonSchema>(
+        &self,
+        con: &mut PgConnection,
+        api_version: &str,
+        kind: &str,
+        aliases: impl IntoIterator<Item = impl Into<String>>,
+        foreign_keys: impl IntoIterator<Item = ForeignKey>,
+        options: &KindOptions,
+    ) -> Result<(), DawnStoreError> {
+        let schema = schemars::schema_for!(T);
+        let properties = schema
+            .get("properties")
+            .and_then(|x| x.as_object())
+            .into_iter()
+            .flat_map(|x| x.keys());
+        for field in properties {
+            if RESERVED_SPEC_FIELDS.contains(&field.as_str()) {
+                return Err(DawnStoreError::ReservedSpecField {
+                    kind: format!("{api_version}/{kind}"),
+                    field: field.clone(),
+                });
+            }
+        }
+        let schema = serde_json::to_string(&schema)?;
+        for rule in &options.validation_rules {
+            apply_impl::compile_validation_rule(rule)?;
+        }
+        let status_schema = match &options.status_schema {
+            Some(x) => Some(serde_json::to_string(x)?),
+            None => None,
+        };
+        let existing = queries::get_object_schema(&mut *con, api_version, kind).await?;
+        let row = ObjectSchema {
+            id: existing.as_ref().map(|x| x.id).unwrap_or_else(Uuid::new_v4),
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+            json_schema: schema,
+            aliases: aliases.into_iter().map(|x| x.into()).collect(),
+            status_json_schema: status_schema,
+            ttl_seconds: options.ttl.map(|x| x.as_secs() as i64),
+            immutable: options.immutable,
+            validation_rules: sqlx::types::Json(options.validation_rules.clone()),
+        };
+        match existing {
+            Some(_) => queries::update_object_schema(&mut *con, &row).await?,
+            None => queries::insert_object_schema(&mut *con, &row).await?,
+        }
+        let existing_keys =
+            queries::get_foreign_key_constraints(&mut *con, api_version, kind).await?;
         let foreign_keys = foreign_keys.into_iter();
         let mut keys = Vec::<ForeignKeyConstraint>::new();
         for key in foreign_keys {
+            if key_path::parse(&key.path).is_none() {
+                return Err(DawnStoreError::InvalidForeignKeyPath(key.path));
+            }
+            let edge_json_schema = match &key.edge_schema {
+                Some(x) => {
+                    jsonschema::validator_for(x.as_value())?;
+                    Some(serde_json::to_string(x)?)
+                }
+                None => None,
+            };
             keys.push(ForeignKeyConstraint {
-                id: Uuid::new_v4(),
-                api_version: api_version.clone(),
-                kind: kind.clone(),
+                id: existing_keys
+                    .iter()
+                    .find(|x| x.key_path == key.path)
+                    .map(|x| x.id)
+                    .unwrap_or_else(Uuid::new_v4),
+                api_version: api_version.to_owned(),
+                kind: kind.to_owned(),
                 key_path: key.path,
                 r#type: key.ty,
                 behaviour: key.behaviour,
-                foreign_key_kind: key.foreign_kind,
+                allowed_kinds: sqlx::types::Json(key.allowed_kinds),
+                acyclic: key.acyclic,
+                edge_json_schema,
+                namespace_policy: sqlx::types::Json(key.namespace_policy),
                 parent_key_path: key.parent_path,
             });
         }
-        queries::insert_multiple_foreign_key_constraints(trans.as_mut(), keys.as_slice()).await?;
-        trans.commit().await?;
-
+        for x in &existing_keys {
+            if !keys.iter().any(|k| k.id == x.id) {
+                queries::delete_foreign_key_constraints(&mut *con, x.id).await?;
+            }
+        }
+        let (updated, inserted): (Vec<_>, Vec<_>) = keys
+            .into_iter()
+            .partition(|k| existing_keys.iter().any(|x| x.id == k.id));
+        for key in &updated {
+            queries::update_foreign_key_constraints(&mut *con, key).await?;
+        }
+        queries::insert_multiple_foreign_key_constraints(con, inserted.as_slice()).await?;
         Ok(())
     }
 
+    /// seeds the kinds the store itself relies on
+    pub async fn seed_builtin_object_schemas(&self) -> Result<(), DawnStoreError> {
+        self.seed_object_schema::<Lease>(
+            LEASE_API_VERSION,
+            LEASE_KIND,
+            ["leases"],
+            Vec::<ForeignKey>::new(),
+        )
+        .await?;
+        self.seed_object_schema_with_options::<Event>(
+            EVENT_API_VERSION,
+            EVENT_KIND,
+            ["events"],
+            Vec::<ForeignKey>::new(),
+            KindOptions::default().with_ttl(events::EVENT_TTL),
+        )
+        .await
+    }
+
     pub async fn sqlx_migrate(&self) -> Result<(), MigrateError> {
         sqlx::migrate!("./migrations").run(&self.pool).await
     }
 
     pub async fn delete(&self, delete: &DeleteObject) -> Result<(), DawnStoreError> {
-        let mut con = self.pool.acquire().await?;
+        let mut events = Vec::new();
+        let result: Result<_, DawnStoreError> = async {
+            let mut con = self.pool.begin().await?;
+            self.delete_in(con.as_mut(), delete, &mut events).await?;
+            con.commit().await?;
+            Ok(())
+        }
+        .await;
+        self.emit_events(events).await;
+        result
+    }
+
+    /// events explaining why the deletion failed are queued in `events`
+    async fn delete_in(
+        &self,
+        con: &mut PgConnection,
+        delete: &DeleteObject,
+        events: &mut Vec<RecordEvent>,
+    ) -> Result<(), DawnStoreError> {
         let ns = match &delete.namespace {
             Some(x) if x == "default" => None,
             Some(x) => Some(x),
             None => None,
         }
         .map(|x| x.as_str());
-        queries::delete_object(&mut con, ns, &delete.name, &delete.kind).await?;
+        let mut objects =
+            queries::get_objects_by_name(&mut *con, ns, &delete.name, &delete.kind).await?;
+        for obj in &objects {
+            lease::check_not_lease(&obj.api_version, &obj.kind, &obj.string_id)?;
+        }
+        let mut blocked = delete_impl::get_blocking_references(&mut *con, &objects).await?;
+        if let Some(obj) = objects.iter().find(|x| blocked.contains_key(&x.id)) {
+            let referenced_by = blocked.remove(&obj.id).unwrap_or_default();
+            events.push(RecordEvent {
+                involved_object: obj.reference(),
+                event_type: EventType::Warning,
+                reason: "DeletionBlocked".to_string(),
+                message: format!("still referenced by {}", referenced_by.join(", ")),
+            });
+            return Err(DawnStoreError::DeletionBlockedByForeignKey {
+                object: obj.string_id.clone(),
+                referenced_by,
+            });
+        }
+        if delete.propagation_policy == Some(PropagationPolicy::Foreground) {
+            // the garbage collector removes the finalizer once the dependents are gone
+            let ids = objects.iter().map(|x| x.id).collect::<Vec<_>>();
+            queries::add_finalizer(&mut *con, ids.as_slice(), FOREGROUND_DELETION_FINALIZER)
+                .await?;
+            objects
+                .iter_mut()
+                .filter(|x| {
+                    !x.finalizers
+                        .iter()
+                        .any(|f| f == FOREGROUND_DELETION_FINALIZER)
+                })
+                .for_each(|x| x.finalizers.push(FOREGROUND_DELETION_FINALIZER.to_string()));
+        }
+        delete_impl::delete_objects(con, objects.as_slice()).await?;
+        Ok(())
+    }
+
+    pub async fn remove_finalizer(&self, remove: &RemoveFinalizer) -> Result<(), DawnStoreError> {
+        let ns = remove.namespace.as_deref().unwrap_or("default");
+        let string_id = format!("{}/{}/{}", ns, remove.kind, remove.name);
+        let mut con = self.pool.begin().await?;
+        let Some(obj) = queries::get_object_by_string_id(con.as_mut(), &string_id).await? else {
+            return Err(DawnStoreError::ObjectNotFound(string_id));
+        };
+        queries::remove_finalizer(con.as_mut(), obj.id, &remove.finalizer).await?;
+        let obj = queries::get_object(con.as_mut(), obj.id).await?;
+        delete_impl::delete_finalized_objects(con.as_mut(), obj.as_slice()).await?;
+        con.commit().await?;
         Ok(())
     }
 
@@ -111,8 +395,22 @@ impl PostgresBackend {
         &self,
         filter: &GetObjectsFilter,
     ) -> Result<Vec<ReturnObject<serde_json::Value>>, DawnStoreError> {
+        if let Some(x) = filter
+            .field_selectors
+            .iter()
+            .flatten()
+            .find(|x| !index_impl::is_plain_path(&x.path))
+        {
+            return Err(DawnStoreError::InvalidFieldSelector(x.path.clone()));
+        }
+        let query = filter
+            .query
+            .as_deref()
+            .map(query::parse_query)
+            .transpose()
+            .map_err(|e| DawnStoreError::InvalidQuery(e.to_string()))?;
         let mut con = self.pool.acquire().await?;
-        let objs = queries::get_objects_by_filter(con.as_mut(), filter).await?;
+        let objs = queries::get_objects_by_filter(con.as_mut(), filter, query.as_ref()).await?;
 
         let obj_ids = objs.iter().map(|x| x.id).collect::<Vec<_>>();
         let relations = queries::get_relations_of_objects(con.as_mut(), obj_ids.as_slice()).await?;
@@ -125,35 +423,10 @@ impl PostgresBackend {
             queries::get_objects(con.as_mut(), foreign_objects.as_slice())
                 .await?
                 .into_iter()
-                .map(|x| ReturnAny {
-                    id: x.id,
-                    namespace: x.namespace,
-                    api_version: x.api_version,
-                    kind: x.kind,
-                    name: x.name,
-                    created_at: x.created_at,
-                    updated_at: x.updated_at,
-                    annotations: Some(x.annotations.0),
-                    labels: Some(x.labels.0),
-                    spec: x.spec.0,
-                })
+                .map(ReturnAny::from)
                 .collect();
 
-        let mut objects = objs
-            .into_iter()
-            .map(|x| ReturnAny {
-                id: x.id,
-                namespace: x.namespace,
-                api_version: x.api_version,
-                kind: x.kind,
-                name: x.name,
-                created_at: x.created_at,
-                updated_at: x.updated_at,
-                annotations: Some(x.annotations.0),
-                labels: Some(x.labels.0),
-                spec: x.spec.0,
-            })
-            .collect();
+        let mut objects = objs.into_iter().map(ReturnAny::from).collect();
 
         if !filter.fill_child_foreign_keys {
             return Ok(objects);
@@ -170,49 +443,67 @@ impl PostgresBackend {
                 ));
             };
             for fkc in x {
-                let fk_ids = relations
+                let Some(segments) = key_path::parse(&fkc.key_path) else {
+                    continue;
+                };
+                let targets = relations
                     .iter()
-                    .filter(|x| x.foreign_key_id == fkc.id)
+                    .filter(|x| x.object_id == obj.id && x.foreign_key_id == fkc.id)
+                    .filter_map(|x| {
+                        let o = foreign_objects
+                            .iter()
+                            .find(|o| o.id == x.foreign_object_id)?;
+                        Some((o, x.properties.as_ref().map(|x| &x.0)))
+                    })
                     .collect::<Vec<_>>();
+                let (ns, kind) = (obj.namespace.clone(), obj.kind.clone());
 
-                let mut objs = foreign_objects
-                    .iter()
-                    .filter(|o| fk_ids.iter().any(|x| x.foreign_object_id == o.id))
-                    .collect::<Vec<_>>();
-
-                let obj_path = format!("{}_object", fkc.key_path);
-                let mut path_segments = obj_path.split(".").collect::<Vec<_>>();
-                let last_segment = path_segments.pop();
-                let mut key_position = &mut obj.spec;
-                for seg in path_segments {
-                    let k = key_position.get(seg).is_none();
-
-                    if k {
-                        if let Value::Object(x) = key_position {
-                            x.insert(seg.to_string(), Value::Object(Default::default()));
-                        } else {
-                            return Err(DawnStoreError::InternalServerError(
-                                "unexpected json value of field".to_string(),
-                            ));
-                        }
+                // every place of the key gets the objects it references next to it
+                key_path::for_each_parent_mut(&mut obj.spec, &segments, &mut |parent,
+                                                                              field|
+                 -> Result<
+                    (),
+                    DawnStoreError,
+                > {
+                    let values = match parent.get(field) {
+                        Some(Value::Array(x)) => x.iter().collect(),
+                        Some(x) => vec![x],
+                        None => vec![],
                     };
-
-                    key_position = key_position.get_mut(seg).unwrap()
-                }
-
-                if let (Some(seg), Value::Object(x)) = (last_segment, key_position) {
-                    let value = match fkc.r#type {
-                        crate::models::ForeignKeyType::One => serde_json::to_value(objs.pop())?,
-                        crate::models::ForeignKeyType::OneOptional => {
-                            serde_json::to_value(objs.pop())?
+                    let string_ids = values
+                        .into_iter()
+                        .filter_map(apply_impl::split_reference)
+                        .filter_map(|(x, _)| apply_impl::resolve_foreign_key(x, &ns, &kind))
+                        .map(|(_, x)| x)
+                        .collect::<Vec<_>>();
+                    let mut objs = targets
+                        .iter()
+                        .filter(|(o, _)| {
+                            string_ids.contains(&format!("{}/{}/{}", o.namespace, o.kind, o.name))
+                        })
+                        .copied()
+                        .collect::<Vec<_>>();
+                    // the properties of the relations next to the objects, in the same shape
+                    let has_properties = objs.iter().any(|(_, x)| x.is_some());
+                    let (value, properties) = match fkc.r#type {
+                        crate::models::ForeignKeyType::OneOrMany => {
+                            let (objs, props): (Vec<_>, Vec<_>) = objs.into_iter().unzip();
+                            (serde_json::to_value(objs)?, serde_json::to_value(props)?)
                         }
-                        crate::models::ForeignKeyType::OneOrMany => serde_json::to_value(objs)?,
-                        crate::models::ForeignKeyType::NoneOrMany => {
-                            serde_json::to_value(objs.pop())?
+                        _ => {
+                            let (obj, props) = objs.pop().unzip();
+                            (
+                                serde_json::to_value(obj)?,
+                                serde_json::to_value(props.flatten())?,
+                            )
                         }
                     };
-                    x.insert(seg.to_string(), value);
-                }
+                    parent.insert(format!("{field}_object"), value);
+                    if has_properties {
+                        parent.insert(format!("{field}_properties"), properties);
+                    }
+                    Ok(())
+                })?;
             }
         }
 
@@ -223,14 +514,36 @@ impl PostgresBackend {
         &self,
         _filter: &GetResourceDefinitionFilter,
     ) -> Result<Vec<ResourceDefinition>, DawnStoreError> {
+        let mut foreign_keys = HashMap::<String, Vec<ForeignKeyDefinition>>::new();
+        for key in queries::get_all_foreign_key_constraints(&self.pool).await? {
+            foreign_keys
+                .entry(format!("{}/{}", key.api_version, key.kind))
+                .or_default()
+                .push(ForeignKeyDefinition {
+                    path: key.key_path,
+                    parent_path: key.parent_key_path,
+                    r#type: format!("{:?}", key.r#type),
+                    allowed_kinds: key.allowed_kinds.0,
+                    acyclic: key.acyclic,
+                    edge_json_schema: key.edge_json_schema,
+                    namespace_policy: key.namespace_policy.0,
+                });
+        }
         let objs = queries::get_all_object_schemas(&self.pool)
             .await?
             .into_iter()
             .map(|x| ResourceDefinition {
+                foreign_keys: foreign_keys
+                    .remove(&format!("{}/{}", x.api_version, x.kind))
+                    .unwrap_or_default(),
                 api_version: x.api_version,
                 kind: x.kind,
                 aliases: x.aliases,
                 json_schema: x.json_schema,
+                status_json_schema: x.status_json_schema,
+                ttl_seconds: x.ttl_seconds,
+                immutable: x.immutable,
+                validation_rules: x.validation_rules.0,
             })
             .collect();
         Ok(objs)
@@ -239,15 +552,146 @@ impl PostgresBackend {
     pub async fn apply_raw(
         &self,
         data: serde_json::Value,
+        options: &ApplyOptions,
     ) -> Result<Vec<ReturnObject<serde_json::Value>>, DawnStoreError> {
         let input_objects = apply_impl::build_base_objects_from_raw_value(data)?;
+        let mut events = Vec::new();
+        let result: Result<_, DawnStoreError> = async {
+            let mut con = self.pool.begin().await?;
+            let (database_objects, pruned) = self
+                .apply_in(con.as_mut(), input_objects, options, &mut events)
+                .await?;
+            con.commit().await?;
+            events.extend(pruned_events(pruned.as_slice()));
+            Ok(database_objects)
+        }
+        .await;
+        self.emit_events(events).await;
+
+        Ok(result?.into_iter().map(ReturnAny::from).collect())
+    }
+
+    /// applies the objects and returns them together with the pruned objects
+    async fn apply_in(
+        &self,
+        con: &mut PgConnection,
+        mut input_objects: Vec<ObjectAny>,
+        options: &ApplyOptions,
+        events: &mut Vec<RecordEvent>,
+    ) -> Result<(Vec<Object>, Vec<Object>), DawnStoreError> {
+        if let Some(set) = &options.applied_set {
+            input_objects.iter_mut().for_each(|x| {
+                x.labels
+                    .get_or_insert_default()
+                    .insert(APPLIED_SET_LABEL.to_string(), set.clone());
+            });
+        }
+        let database_objects = self
+            .apply_objects(&mut *con, input_objects, options, events)
+            .await?;
+        let pruned = match options.prune {
+            true => {
+                delete_impl::prune_objects(&mut *con, database_objects.as_slice(), options).await?
+            }
+            false => vec![],
+        };
+        Ok((database_objects, pruned))
+    }
+
+    /// patches labels, annotations and spec of a stored object and applies the
+    /// result, the object stays locked in between so test operations hold
+    pub async fn patch(&self, patch: &PatchObject) -> Result<ReturnAny, DawnStoreError> {
+        let mut events = Vec::new();
+        let result: Result<_, DawnStoreError> = async {
+            let mut con = self.pool.begin().await?;
+            let obj = self.patch_in(con.as_mut(), patch, &mut events).await?;
+            con.commit().await?;
+            Ok(obj)
+        }
+        .await;
+        self.emit_events(events).await;
+        Ok(result?.into())
+    }
+
+    async fn patch_in(
+        &self,
+        con: &mut PgConnection,
+        patch: &PatchObject,
+        events: &mut Vec<RecordEvent>,
+    ) -> Result<Object, DawnStoreError> {
+        let ns = patch.namespace.as_deref().unwrap_or("default");
+        let string_id = format!("{}/{}/{}", ns, patch.kind, patch.name);
+        let Some(obj) = queries::lock_object_by_string_id(&mut *con, &string_id).await? else {
+            return Err(DawnStoreError::ObjectNotFound(string_id));
+        };
+        let obj = patch_impl::patch_object(obj, patch.patch_type, &patch.patch)?;
+        let options = ApplyOptions {
+            field_manager: Some(PATCH_FIELD_MANAGER.to_string()),
+            ..Default::default()
+        };
+        let mut database_objects = self.apply_objects(con, vec![obj], &options, events).await?;
+        database_objects.pop().ok_or_else(|| {
+            DawnStoreError::InternalServerError("patched object was not written".to_string())
+        })
+    }
 
+    /// validates and writes the objects including their relations, the caller
+    /// decides about the transaction and records the queued `events` after it
+    async fn apply_objects(
+        &self,
+        con: &mut PgConnection,
+        input_objects: Vec<ObjectAny>,
+        options: &ApplyOptions,
+        events: &mut Vec<RecordEvent>,
+    ) -> Result<Vec<Object>, DawnStoreError> {
+        let references = input_objects
+            .iter()
+            .filter_map(|x| {
+                let ns = x.namespace.as_deref().unwrap_or("default");
+                let kind = x.kind.as_ref()?;
+                let reference = ObjectReference {
+                    namespace: ns.to_owned(),
+                    api_version: x.api_version.clone(),
+                    kind: kind.clone(),
+                    name: x.name.clone(),
+                };
+                Some((format!("{}/{}/{}", ns, kind, x.name), reference))
+            })
+            .collect::<HashMap<_, _>>();
+        let result = self.write_objects(con, input_objects, options).await;
+        if let Err(e) = &result {
+            events.extend(validation_failed_events(&references, e));
+        }
+        result
+    }
+
+    async fn write_objects(
+        &self,
+        con: &mut PgConnection,
+        input_objects: Vec<ObjectAny>,
+        options: &ApplyOptions,
+    ) -> Result<Vec<Object>, DawnStoreError> {
+        let (input_objects, stored) =
+            apply_impl::merge_with_stored_objects(&mut *con, input_objects, options).await?;
         // validate if objects have all required fields and if the underlying schema is sound
         let mut string_ids = Vec::<String>::with_capacity(input_objects.len());
         let mut input_objects_with_string_id = Vec::<(String, ObjectAny)>::new();
         let mut all_fks = HashMap::<String, Vec<(Vec<String>, Uuid)>>::default();
+        let mut all_edges = HashMap::<String, apply_impl::Edges>::default();
+        let payload = input_objects
+            .iter()
+            .filter_map(|x| {
+                let ns = x.namespace.as_deref().unwrap_or("default");
+                x.kind
+                    .as_ref()
+                    .map(|kind| format!("{}/{}/{}", ns, kind, x.name))
+            })
+            .collect::<Vec<_>>();
+        let mut unique_keys = HashMap::<unique_impl::UniqueKey, String>::new();
+        // validation errors of all objects, reported together after the checks
+        let mut errors = Vec::<ObjectError>::new();
+        let mut references = HashMap::<String, ObjectReference>::new();
 
-        // let mut schema_cache = self.schema_cache.read().await;
         for obj in input_objects {
             let Some(api_version) = &obj.api_version else {
                 return Err(DawnStoreError::ApiVersionMissingInObject);
@@ -258,33 +702,114 @@ impl PostgresBackend {
             let ns = obj.namespace.as_deref().unwrap_or("default");
             let object_id = format!("{api_version}/{kind}");
             let string_id = format!("{}/{}/{}", ns, kind, obj.name,);
+            lease::check_not_lease(api_version, kind, &string_id)?;
+            references.insert(
+                string_id.clone(),
+                ObjectReference {
+                    namespace: ns.to_owned(),
+                    api_version: Some(api_version.clone()),
+                    kind: kind.clone(),
+                    name: obj.name.clone(),
+                },
+            );
+            let mut object_errors = Vec::<DawnStoreError>::new();
 
-            let mut con = self.pool.acquire().await?;
-            apply_impl::validate_object_schema(
-                &mut con,
-                &self.schema_cache,
-                &obj,
-                api_version,
-                kind,
-                &object_id,
-            )
-            .await?;
+            collect_validation_error(
+                &mut object_errors,
+                apply_impl::validate_object_schema(
+                    &mut *con,
+                    &self.schema_cache,
+                    &obj,
+                    stored.get(&string_id).map(|x| &x.spec.0),
+                    api_version,
+                    kind,
+                    &object_id,
+                )
+                .await,
+            )?;
+
+            collect_validation_error(
+                &mut object_errors,
+                apply_impl::check_immutable_fields(
+                    &self.schema_cache,
+                    &obj,
+                    stored.get(&string_id),
+                    &object_id,
+                    &string_id,
+                )
+                .await,
+            )?;
+
+            collect_validation_error(
+                &mut object_errors,
+                apply_impl::check_owners(&mut *con, &obj, ns, &string_id).await,
+            )?;
 
             // check if the foreign keys are valid
-            let fks = apply_impl::check_foreign_keys(
-                &mut con,
-                &self.foreign_key_cache,
-                &obj,
-                api_version,
-                kind,
-                ns,
-                object_id,
-            )
-            .await?;
+            let checked = collect_validation_error(
+                &mut object_errors,
+                apply_impl::check_foreign_keys(
+                    &mut *con,
+                    &self.foreign_key_cache,
+                    &obj,
+                    api_version,
+                    kind,
+                    ns,
+                    object_id.clone(),
+                )
+                .await,
+            )?;
+            let (fks, edges) = checked.unzip();
+
+            // the scopes of foreign key constraints are only known with valid keys
+            if let Some(fks) = &fks {
+                let constraints = self
+                    .schema_cache
+                    .read()
+                    .await
+                    .get(&object_id)
+                    .map(|x| x.unique_constraints.clone())
+                    .unwrap_or_default();
+                let fk_cache = self.foreign_key_cache.read().await;
+                let keys = collect_validation_error(
+                    &mut object_errors,
+                    unique_impl::check_unique_constraints(
+                        &mut *con,
+                        constraints.as_slice(),
+                        fk_cache
+                            .get(&object_id)
+                            .map(|x| x.as_slice())
+                            .unwrap_or_default(),
+                        &obj,
+                        ns,
+                        &string_id,
+                        fks.as_slice(),
+                        payload.as_slice(),
+                    )
+                    .await,
+                )?;
+                for key in keys.into_iter().flatten() {
+                    match unique_keys.insert(key.clone(), string_id.clone()) {
+                        Some(other) if other != string_id => {
+                            object_errors.push(DawnStoreError::UniqueConstraintViolation {
+                                constraint: key.0,
+                                object: string_id.clone(),
+                                conflicting: other,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
 
+            errors.extend(object_errors.into_iter().map(|error| ObjectError {
+                object: string_id.clone(),
+                error,
+            }));
             string_ids.push(string_id.clone());
             input_objects_with_string_id.push((string_id.clone(), obj));
-            all_fks.insert(string_id, fks);
+            all_edges.insert(string_id.clone(), edges.unwrap_or_default());
+            all_fks.insert(string_id, fks.unwrap_or_default());
         }
 
         let mut all_string_ids = HashSet::<&str>::new();
@@ -303,19 +828,83 @@ impl PostgresBackend {
             .map(|x| x.to_owned())
             .collect::<Vec<String>>();
 
-        let mut con = self.pool.begin().await?;
-        let mut object_infos = queries::get_object_infos(con.as_mut(), all_string_ids.as_slice())
+        let mut object_infos = queries::get_object_infos(&mut *con, all_string_ids.as_slice())
             .await?
             .into_iter()
             .map(|x| (x.string_id.clone(), x))
             .collect::<HashMap<String, ObjectInfo>>();
+
+        // referenced objects have to be stored or part of the payload
+        {
+            let fk_cache = self.foreign_key_cache.read().await;
+            for (string_id, obj) in &input_objects_with_string_id {
+                let api_version = obj.api_version.clone().unwrap_or_default();
+                let kind = obj.kind.clone().unwrap_or_default();
+                let foreign_keys = fk_cache.get(&format!("{api_version}/{kind}"));
+                for (targets, fk_id) in all_fks.get(string_id).into_iter().flatten() {
+                    let Some(key) = foreign_keys.and_then(|x| x.iter().find(|x| x.id == *fk_id))
+                    else {
+                        continue;
+                    };
+                    for target in targets {
+                        let pointer = all_edges
+                            .get(string_id)
+                            .and_then(|x| x.get(&(*fk_id, target.clone())))
+                            .map(|x| x.pointer.clone())
+                            .unwrap_or_default();
+                        // the payload version wins, the object is about to be updated
+                        let target_api_version = match references.get(target) {
+                            Some(x) => x.api_version.as_deref(),
+                            None => object_infos.get(target).map(|x| x.api_version.as_str()),
+                        };
+                        let Some(target_api_version) = target_api_version else {
+                            errors.push(ObjectError {
+                                object: string_id.clone(),
+                                error: DawnStoreError::ObjectValidationForeignKeyNotFound {
+                                    api_version: api_version.clone(),
+                                    kind: kind.clone(),
+                                    name: obj.name.clone(),
+                                    pointer: pointer.clone(),
+                                    value: target.clone(),
+                                },
+                            });
+                            continue;
+                        };
+                        if apply_impl::allows_api_version(key, target, target_api_version) {
+                            continue;
+                        }
+                        errors.push(ObjectError {
+                            object: string_id.clone(),
+                            error: DawnStoreError::ObjectValidationWrongForeignKeyEntryKind {
+                                api_version: api_version.clone(),
+                                kind: kind.clone(),
+                                name: obj.name.clone(),
+                                pointer: pointer.clone(),
+                                foreign_key_type: key.r#type.clone(),
+                                value: format!("{target_api_version}/{target}"),
+                                allowed_kinds: key.allowed_kinds.0.clone(),
+                            },
+                        });
+                    }
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(DawnStoreError::ValidationFailed(errors));
+        }
+
         let all_object_db_ids = all_fks
             .keys()
             .filter_map(|x| object_infos.get(x).map(|x| x.id))
             .collect::<Vec<_>>();
-        let database_objects =
-            apply_impl::maintain_objects(con.as_mut(), &object_infos, input_objects_with_string_id)
-                .await?;
+        let database_objects = apply_impl::maintain_objects(
+            &mut *con,
+            &self.schema_cache,
+            &object_infos,
+            input_objects_with_string_id,
+        )
+        .await?;
         database_objects.iter().for_each(|x| {
             let string_id = format!("{}/{}/{}", x.namespace, x.kind, x.name);
             object_infos.insert(
@@ -323,7 +912,13 @@ impl PostgresBackend {
                 ObjectInfo {
                     id: x.id,
                     string_id,
+                    api_version: x.api_version.clone(),
                     created_at: x.created_at,
+                    status: x.status.clone(),
+                    finalizers: x.finalizers.clone(),
+                    owners: x.owners.clone(),
+                    expires_at: x.expires_at,
+                    deletion_timestamp: x.deletion_timestamp,
                 },
             );
         });
@@ -342,13 +937,18 @@ impl PostgresBackend {
                         object_id: oi.id,
                         foreign_object_id: foi.id,
                         foreign_key_id: *fk_id,
+                        properties: all_edges
+                            .get(object_id)
+                            .and_then(|x| x.get(&(*fk_id, sid.clone())))
+                            .and_then(|x| x.properties.clone())
+                            .map(sqlx::types::Json),
                     });
                 }
             }
         }
 
         let existing_relations =
-            queries::get_relations_of_objects(con.as_mut(), all_object_db_ids.as_slice()).await?;
+            queries::get_relations_of_objects(&mut *con, all_object_db_ids.as_slice()).await?;
 
         let relations_to_delete = existing_relations
             .into_iter()
@@ -374,30 +974,88 @@ impl PostgresBackend {
             .collect::<Vec<_>>();
 
         queries::delete_multiple_relations(
-            con.as_mut(),
+            &mut *con,
             object_ids_to_delete.as_slice(),
             fko_ids_to_delete.as_slice(),
             fk_ids_to_delete.as_slice(),
         )
         .await?;
-        queries::insert_multiple_relation(con.as_mut(), foreign_key_objects.as_slice()).await?;
-        con.commit().await?;
+        queries::insert_multiple_relation(&mut *con, foreign_key_objects.as_slice()).await?;
+        self.check_acyclic_foreign_keys(&mut *con, foreign_key_objects.as_slice())
+            .await?;
+        // checked before, a violation here is a write the check did not see
+        let object_ids = database_objects.iter().map(|x| x.id).collect::<Vec<_>>();
+        queries::sync_unique_foreign_key_values(&mut *con, object_ids.as_slice()).await?;
+        delete_impl::delete_finalized_objects(&mut *con, database_objects.as_slice()).await?;
 
-        Ok(database_objects
-            .into_iter()
-            .map(|x| ReturnAny {
-                id: x.id,
-                namespace: x.namespace,
-                api_version: x.api_version,
-                kind: x.kind,
-                name: x.name,
-                created_at: x.created_at,
-                updated_at: x.updated_at,
-                annotations: Some(x.annotations.0),
-                labels: Some(x.labels.0),
-                spec: x.spec.0,
-            })
-            .collect())
+        Ok(database_objects)
+    }
+
+    /// runs after the relations are written, so cycles closed by several
+    /// objects of the same apply are found as well
+    async fn check_acyclic_foreign_keys(
+        &self,
+        con: &mut PgConnection,
+        relations: &[Relation],
+    ) -> Result<(), DawnStoreError> {
+        let acyclic_keys = self
+            .foreign_key_cache
+            .read()
+            .await
+            .values()
+            .flatten()
+            .filter(|x| x.acyclic)
+            .map(|x| (x.id, x.key_path.clone()))
+            .collect::<Vec<_>>();
+        for (foreign_key_id, foreign_key_path) in acyclic_keys {
+            let object_ids = relations
+                .iter()
+                .filter(|x| x.foreign_key_id == foreign_key_id)
+                .map(|x| x.object_id)
+                .collect::<Vec<_>>();
+            if object_ids.is_empty() {
+                continue;
+            }
+            let cycle =
+                queries::find_relation_cycle(&mut *con, foreign_key_id, object_ids.as_slice())
+                    .await?;
+            if !cycle.is_empty() {
+                return Err(DawnStoreError::ForeignKeyCycle {
+                    foreign_key_path,
+                    cycle,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn update_status(
+        &self,
+        update: &UpdateObjectStatus,
+    ) -> Result<ReturnAny, DawnStoreError> {
+        let ns = update.namespace.as_deref().unwrap_or("default");
+        let string_id = format!("{}/{}/{}", ns, update.kind, update.name);
+        let mut con = self.pool.begin().await?;
+        let Some(mut obj) = queries::get_object_by_string_id(con.as_mut(), &string_id).await?
+        else {
+            return Err(DawnStoreError::ObjectNotFound(string_id));
+        };
+        lease::check_not_lease(&obj.api_version, &obj.kind, &string_id)?;
+        apply_impl::validate_object_status(
+            con.as_mut(),
+            &self.schema_cache,
+            &update.status,
+            &obj.api_version,
+            &obj.kind,
+            &obj.name,
+        )
+        .await?;
+        // the status is not part of the desired state, so updated_at stays untouched
+        obj.resource_version =
+            queries::update_object_status(con.as_mut(), obj.id, &update.status).await?;
+        con.commit().await?;
+        obj.status = Some(sqlx::types::Json(update.status.clone()));
+        Ok(obj.into())
     }
 
     pub async fn get_object_infos(
@@ -418,4 +1076,23 @@ impl PostgresBackend {
             .collect();
         Ok(ObjectInfos { infos: objs })
     }
+
+    pub async fn search(&self, filter: &SearchFilter) -> Result<SearchResults, DawnStoreError> {
+        let limit = filter.limit.unwrap_or(50).min(250) as i64;
+        let mut con = self.pool.acquire().await?;
+        let hits = queries::search_objects(con.as_mut(), filter, limit)
+            .await?
+            .into_iter()
+            .map(|x| SearchHit {
+                namespace: x.namespace,
+                id: x.id,
+                api_version: x.api_version,
+                kind: x.kind,
+                name: x.name,
+                rank: x.rank,
+                headline: x.headline,
+            })
+            .collect();
+        Ok(SearchResults { hits })
+    }
 }
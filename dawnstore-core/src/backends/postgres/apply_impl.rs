@@ -1,17 +1,19 @@
 use std::collections::HashMap;
 
-use chrono::Utc;
-use serde_json::Value;
+use chrono::{TimeDelta, Utc};
+use serde_json::{Map, Value};
 use sqlx::PgConnection;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
     backends::postgres::{
-        data_models::{ForeignKeyConstraint, Object, ObjectInfo},
-        queries,
+        data_models::{ForeignKeyConstraint, Object, ObjectInfo, UniqueConstraintRow},
+        key_path, queries,
     },
-    error::DawnStoreError,
+    error::{DawnStoreError, ObjectError},
+    expression::Expression,
+    merge::{self, ObjectDocument},
     models::ForeignKeyType,
 };
 
@@ -53,39 +55,282 @@ pub fn build_base_objects_from_raw_value(
     Ok(input_objects)
 }
 
+pub struct KindSchema {
+    pub spec: jsonschema::Validator,
+    pub status: Option<jsonschema::Validator>,
+    pub ttl: Option<TimeDelta>,
+    pub unique_constraints: Vec<UniqueConstraintRow>,
+    pub immutable: bool,
+    /// json pointers of the spec fields marked as immutable
+    pub immutable_paths: Vec<String>,
+    pub validation_rules: Vec<(ValidationRule, Expression)>,
+}
+
+/// a reference of an object to another one
+pub struct Edge {
+    /// json pointer of the first place in the spec referencing the object
+    pub pointer: String,
+    pub properties: Option<Value>,
+}
+
+/// the references by foreign key id and referenced string id
+pub type Edges = HashMap<(Uuid, String), Edge>;
+
+pub fn compile_validation_rule(rule: &ValidationRule) -> Result<Expression, DawnStoreError> {
+    Expression::parse(&rule.rule).map_err(|e| DawnStoreError::InvalidValidationRule {
+        rule: rule.rule.clone(),
+        error: e.to_string(),
+    })
+}
+
+fn check_validation_rules(
+    rules: &[(ValidationRule, Expression)],
+    spec: &Value,
+    old_spec: Option<&Value>,
+    object: &str,
+) -> Result<(), DawnStoreError> {
+    // every failing rule is reported, like the violations of the schema
+    let mut errors = Vec::new();
+    for (rule, expr) in rules {
+        let pointer = rule
+            .path
+            .as_ref()
+            .map(|x| format!("/{}", x.replace('.', "/")))
+            .unwrap_or_default();
+        let Some(value) = spec.pointer(&pointer) else {
+            continue;
+        };
+        let old_value = old_spec.and_then(|x| x.pointer(&pointer));
+        if expr.is_transition_rule() && old_value.is_none() {
+            continue;
+        }
+        let message = match expr.evaluate(value, old_value) {
+            Ok(true) => continue,
+            Ok(false) => rule
+                .message
+                .clone()
+                .unwrap_or_else(|| format!("failed rule: {}", rule.rule)),
+            Err(e) => format!("rule could not be evaluated: {e}"),
+        };
+        errors.push(ObjectError {
+            object: object.to_owned(),
+            error: DawnStoreError::ValidationRuleFailed {
+                object: object.to_owned(),
+                rule: rule.rule.clone(),
+                path: match &rule.path {
+                    Some(x) => format!("spec.{x}"),
+                    None => "spec".to_string(),
+                },
+                message,
+            },
+        });
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(DawnStoreError::ValidationFailed(errors)),
+    }
+}
+
+/// collects the fields carrying the immutable schema extension, references
+/// are followed once per branch as schemas can be recursive
+fn immutable_paths(root: &Value) -> Vec<String> {
+    fn collect<'a>(
+        root: &'a Value,
+        schema: &'a Value,
+        prefix: String,
+        visited: &mut Vec<&'a str>,
+        paths: &mut Vec<String>,
+    ) {
+        if !prefix.is_empty() && schema.get(IMMUTABLE_SCHEMA_EXTENSION) == Some(&Value::Bool(true))
+        {
+            paths.push(prefix);
+            return;
+        }
+        if let Some(Value::String(x)) = schema.get("$ref")
+            && let Some(target) = x.strip_prefix('#').and_then(|p| root.pointer(p))
+            && !visited.contains(&x.as_str())
+        {
+            visited.push(x);
+            collect(root, target, prefix.clone(), visited, paths);
+            visited.pop();
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (k, v) in properties {
+                let path = format!("{prefix}/{}", k.replace('~', "~0").replace('/', "~1"));
+                collect(root, v, path, visited, paths);
+            }
+        }
+        for key in ["anyOf", "oneOf", "allOf"] {
+            if let Some(Value::Array(variants)) = schema.get(key) {
+                for v in variants {
+                    collect(root, v, prefix.clone(), visited, paths);
+                }
+            }
+        }
+    }
+    let mut paths = Vec::new();
+    collect(root, root, String::new(), &mut Vec::new(), &mut paths);
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+async fn ensure_kind_schema_cached(
+    pool: &mut PgConnection,
+    sc: &RwLock<HashMap<String, KindSchema>>,
+    api_version: &str,
+    kind: &str,
+    object_id: &String,
+) -> Result<(), DawnStoreError> {
+    if sc.read().await.contains_key(object_id) {
+        return Ok(());
+    }
+    let Some(schema) = queries::get_object_schema(pool, api_version, kind).await? else {
+        return Err(DawnStoreError::NoSchemaForObjectFound {
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+        });
+    };
+    let json_schema = serde_json::from_str(&schema.json_schema)?;
+    let spec = jsonschema::validator_for(&json_schema)?;
+    let status = match &schema.status_json_schema {
+        Some(x) => Some(jsonschema::validator_for(&serde_json::from_str(x)?)?),
+        None => None,
+    };
+    let ttl = schema.ttl_seconds.map(TimeDelta::seconds);
+    let unique_constraints = queries::get_unique_constraints(pool, api_version, kind).await?;
+    let validation_rules = schema
+        .validation_rules
+        .0
+        .into_iter()
+        .map(|x| compile_validation_rule(&x).map(|expr| (x, expr)))
+        .collect::<Result<Vec<_>, _>>()?;
+    sc.write().await.insert(
+        object_id.clone(),
+        KindSchema {
+            spec,
+            status,
+            ttl,
+            unique_constraints,
+            immutable: schema.immutable,
+            immutable_paths: immutable_paths(&json_schema),
+            validation_rules,
+        },
+    );
+    Ok(())
+}
+
+/// validates the spec against the json schema and the validation rules of
+/// the kind, old_spec is the stored spec for the transition rules
 pub async fn validate_object_schema(
     pool: &mut PgConnection,
-    sc: &RwLock<HashMap<String, jsonschema::Validator>>,
+    sc: &RwLock<HashMap<String, KindSchema>>,
     obj: &dawnstore_lib::Object<Value>,
+    old_spec: Option<&Value>,
     api_version: &str,
     kind: &str,
     object_id: &String,
 ) -> Result<(), DawnStoreError> {
-    let mut schema_cache = sc.read().await;
-    let validator = match schema_cache.get(object_id) {
-        Some(x) => x,
-        None => {
-            drop(schema_cache);
-            let Some(schema) = queries::get_object_schema(pool, api_version, kind).await? else {
-                return Err(DawnStoreError::NoSchemaForObjectFound {
-                    api_version: api_version.to_owned(),
-                    kind: kind.to_owned(),
-                });
-            };
-            let validator = jsonschema::validator_for(&serde_json::from_str(&schema.json_schema)?)?;
-            sc.write().await.insert(object_id.clone(), validator);
-            schema_cache = sc.read().await;
-            schema_cache
-                .get(object_id)
-                .expect("we just added this thing")
-        }
+    ensure_kind_schema_cached(pool, sc, api_version, kind, object_id).await?;
+    let schema_cache = sc.read().await;
+    let schema = schema_cache
+        .get(object_id)
+        .expect("we just added this thing");
+
+    let ns = obj.namespace.as_deref().unwrap_or("default");
+    let string_id = format!("{ns}/{kind}/{}", obj.name);
+    // all violations are reported, the rules only run on a valid spec
+    let errors = schema
+        .spec
+        .iter_errors(&obj.spec)
+        .map(|e| ObjectError {
+            object: string_id.clone(),
+            error: DawnStoreError::ObjectValidationError {
+                api_version: api_version.to_owned(),
+                kind: kind.to_owned(),
+                name: obj.name.clone(),
+                validation_error: e.to_owned(),
+            },
+        })
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(DawnStoreError::ValidationFailed(errors));
+    }
+
+    check_validation_rules(
+        schema.validation_rules.as_slice(),
+        &obj.spec,
+        old_spec,
+        &string_id,
+    )?;
+
+    Ok(())
+}
+
+/// rejects changes of the immutable fields of a stored object, the paths are
+/// reported as json pointers, so keys containing dots stay unambiguous
+pub async fn check_immutable_fields(
+    sc: &RwLock<HashMap<String, KindSchema>>,
+    obj: &dawnstore_lib::Object<Value>,
+    stored: Option<&Object>,
+    object_id: &str,
+    string_id: &str,
+) -> Result<(), DawnStoreError> {
+    let Some(stored) = stored else {
+        return Ok(());
+    };
+    let schema_cache = sc.read().await;
+    let Some(schema) = schema_cache.get(object_id) else {
+        return Ok(());
+    };
+    let new = &obj.spec;
+    let old = &stored.spec.0;
+    let mut changed = if schema.immutable {
+        let mut paths = merge::field_paths(old);
+        paths.extend(merge::field_paths(new));
+        paths
+    } else {
+        schema.immutable_paths.clone()
+    };
+    changed.retain(|x| old.pointer(x) != new.pointer(x));
+    if changed.is_empty() {
+        return Ok(());
+    }
+    changed.sort();
+    changed.dedup();
+    Err(DawnStoreError::ImmutableFieldChanged {
+        object: string_id.to_owned(),
+        paths: changed,
+    })
+}
+
+pub async fn validate_object_status(
+    pool: &mut PgConnection,
+    sc: &RwLock<HashMap<String, KindSchema>>,
+    status: &Value,
+    api_version: &str,
+    kind: &str,
+    name: &str,
+) -> Result<(), DawnStoreError> {
+    let object_id = format!("{api_version}/{kind}");
+    ensure_kind_schema_cached(pool, sc, api_version, kind, &object_id).await?;
+    let schema_cache = sc.read().await;
+    let Some(validator) = &schema_cache
+        .get(&object_id)
+        .expect("we just added this thing")
+        .status
+    else {
+        return Err(DawnStoreError::NoStatusSchemaForObjectFound {
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+        });
     };
 
-    if let Err(e) = validator.validate(&obj.spec) {
-        return Err(DawnStoreError::ObjectValidationError {
+    if let Err(e) = validator.validate(status) {
+        return Err(DawnStoreError::ObjectStatusValidationError {
             api_version: api_version.to_owned(),
             kind: kind.to_owned(),
-            name: obj.name.clone(),
+            name: name.to_owned(),
             validation_error: e.to_owned(),
         });
     };
@@ -101,7 +346,7 @@ pub async fn check_foreign_keys(
     kind: &str,
     ns: &str,
     type_id: String,
-) -> Result<Vec<(Vec<String>, Uuid)>, DawnStoreError> {
+) -> Result<(Vec<(Vec<String>, Uuid)>, Edges), DawnStoreError> {
     let mut foreign_key_cache = fkc.read().await;
     let foreign_keys = match foreign_key_cache.get(&type_id) {
         Some(x) => x,
@@ -116,121 +361,268 @@ pub async fn check_foreign_keys(
         }
     };
 
+    let string_id = format!("{ns}/{kind}/{}", obj.name);
+    let mut errors = Vec::<ObjectError>::new();
     let mut fk_string_ids: Vec<(Vec<String>, Uuid)> = Default::default();
-    'outer: for key in foreign_keys {
-        let path_segments = key.key_path.split(".");
-        let mut key_position = None::<&Value>;
-        for seg in path_segments {
-            let k = match key_position {
-                Some(x) => x.get(seg),
-                None => obj.spec.get(seg),
-            };
-            key_position = match k {
-                Some(x) => Some(x),
-                None if key.r#type == ForeignKeyType::OneOptional => continue 'outer,
-                None if key.r#type == ForeignKeyType::NoneOrMany => continue 'outer,
-                None => {
-                    return Err(DawnStoreError::ObjectValidationMissingForeignKeyEntry {
-                        api_version: api_version.to_owned(),
-                        kind: kind.to_owned(),
-                        name: obj.name.clone(),
-                        foreign_key_path: key.key_path.clone(),
-                        foreign_key_type: key.r#type.clone(),
-                    });
-                }
-            };
-        }
-
-        let Some(key_position) = key_position else {
-            return Err(DawnStoreError::ObjectValidationMissingForeignKeyEntry {
-                api_version: api_version.to_owned(),
-                kind: kind.to_owned(),
-                name: obj.name.clone(),
-                foreign_key_path: key.key_path.clone(),
-                foreign_key_type: key.r#type.clone(),
-            });
-        };
-
-        let foreign_key_values = match (&key.r#type, key_position) {
-            (ForeignKeyType::One, Value::String(x)) => vec![x],
-            (ForeignKeyType::OneOptional, Value::Null) => vec![],
-            (ForeignKeyType::OneOptional, Value::String(x)) => vec![x],
-            (ForeignKeyType::OneOrMany, Value::String(x)) => vec![x],
-            (ForeignKeyType::OneOrMany, Value::Array(values)) if !values.is_empty() => values
-                .iter()
-                .filter_map(|x| match x {
-                    Value::String(x) => Some(x),
-                    _ => None,
-                })
-                .collect(),
-            (ForeignKeyType::NoneOrMany, Value::Null) => vec![],
-            (ForeignKeyType::NoneOrMany, Value::String(x)) => vec![x],
-            (ForeignKeyType::NoneOrMany, Value::Array(values)) => values
-                .iter()
-                .filter_map(|x| match x {
-                    Value::String(x) => Some(x),
-                    _ => None,
-                })
-                .collect(),
-            _ => {
-                return Err(DawnStoreError::ObjectValidationMissingForeignKeyEntry {
+    let mut edges = Edges::new();
+    for key in foreign_keys {
+        let error = |pointer: &str, value: Option<&str>| ObjectError {
+            object: string_id.clone(),
+            error: match value {
+                None => DawnStoreError::ObjectValidationMissingForeignKeyEntry {
                     api_version: api_version.to_owned(),
                     kind: kind.to_owned(),
                     name: obj.name.clone(),
-                    foreign_key_path: key.key_path.clone(),
+                    pointer: pointer.to_owned(),
                     foreign_key_type: key.r#type.clone(),
-                });
-            }
+                },
+                Some(value) => DawnStoreError::ObjectValidationWrongForeignKeyEntryFormat {
+                    api_version: api_version.to_owned(),
+                    kind: kind.to_owned(),
+                    name: obj.name.clone(),
+                    pointer: pointer.to_owned(),
+                    foreign_key_type: key.r#type.clone(),
+                    value: value.to_owned(),
+                },
+            },
+        };
+        // checked when the kind is seeded
+        let Some(segments) = key_path::parse(&key.key_path) else {
+            return Err(DawnStoreError::InvalidForeignKeyPath(key.key_path.clone()));
         };
 
-        let mut fks = Vec::with_capacity(foreign_key_values.len());
-        for fk_val in foreign_key_values {
-            let comps = fk_val.split("/").collect::<Vec<_>>();
-            let (ns, fk_kind, fk_name) = match comps.as_slice() {
-                [ns, kind, name] => (*ns, *kind, *name),
-                // assume same ns as the current object
-                [kind, name] => (ns, *kind, *name),
-                // assume same ns and kind as the current object
-                [name] => (ns, kind, *name),
+        // a reference without properties has to be valid for the schema as well
+        let edge_validator = match &key.edge_json_schema {
+            Some(x) => Some(jsonschema::validator_for(&serde_json::from_str(x)?)?),
+            None => None,
+        };
+
+        // a path through arrays references from every element
+        let mut fks = Vec::new();
+        'places: for place in key_path::resolve(&obj.spec, &segments) {
+            let foreign_key_values = match (&key.r#type, place.value) {
+                (ForeignKeyType::OneOptional | ForeignKeyType::NoneOrMany, None) => vec![],
+                (ForeignKeyType::OneOptional, Some(Value::Null)) => vec![],
+                (ForeignKeyType::NoneOrMany, Some(Value::Null)) => vec![],
+                (_, Some(x @ (Value::String(_) | Value::Object(_)))) => vec![x],
+                (ForeignKeyType::OneOrMany, Some(Value::Array(values))) if !values.is_empty() => {
+                    values
+                        .iter()
+                        .filter(|x| matches!(x, Value::String(_) | Value::Object(_)))
+                        .collect()
+                }
+                (ForeignKeyType::NoneOrMany, Some(Value::Array(values))) => values
+                    .iter()
+                    .filter(|x| matches!(x, Value::String(_) | Value::Object(_)))
+                    .collect(),
                 _ => {
-                    return Err(DawnStoreError::ObjectValidationWrongForeignKeyEntryFormat {
+                    errors.push(error(&place.pointer, None));
+                    continue 'places;
+                }
+            };
+
+            for fk_val in foreign_key_values {
+                let shown = match fk_val {
+                    Value::String(x) => x.clone(),
+                    x => x.to_string(),
+                };
+                let Some((reference, props)) = split_reference(fk_val) else {
+                    errors.push(error(&place.pointer, Some(&shown)));
+                    continue 'places;
+                };
+                let Some((fk_kind, fk_string_id)) = resolve_foreign_key(reference, ns, kind) else {
+                    errors.push(error(&place.pointer, Some(&shown)));
+                    continue 'places;
+                };
+
+                if !key.allowed_kinds.is_empty()
+                    && !key.allowed_kinds.iter().any(|x| x.kind == fk_kind)
+                {
+                    errors.push(ObjectError {
+                        object: string_id.clone(),
+                        error: DawnStoreError::ObjectValidationWrongForeignKeyEntryKind {
+                            api_version: api_version.to_owned(),
+                            kind: kind.to_owned(),
+                            name: obj.name.clone(),
+                            pointer: place.pointer.clone(),
+                            foreign_key_type: key.r#type.clone(),
+                            value: shown,
+                            allowed_kinds: key.allowed_kinds.0.clone(),
+                        },
+                    });
+                    continue 'places;
+                }
+
+                let foreign_ns = fk_string_id.split('/').next().unwrap_or_default();
+                if !key.namespace_policy.allows(ns, foreign_ns) {
+                    errors.push(ObjectError {
+                        object: string_id.clone(),
+                        error: DawnStoreError::ObjectValidationForeignKeyNamespaceNotAllowed {
+                            api_version: api_version.to_owned(),
+                            kind: kind.to_owned(),
+                            name: obj.name.clone(),
+                            pointer: place.pointer.clone(),
+                            value: shown,
+                            policy: key.namespace_policy.0.clone(),
+                        },
+                    });
+                    continue 'places;
+                }
+
+                let property_error = |pointer: String, message: String| ObjectError {
+                    object: string_id.clone(),
+                    error: DawnStoreError::ObjectValidationForeignKeyPropertiesError {
                         api_version: api_version.to_owned(),
                         kind: kind.to_owned(),
                         name: obj.name.clone(),
-                        foreign_key_path: key.key_path.clone(),
-                        foreign_key_type: key.r#type.clone(),
-                        value: fk_val.clone(),
-                    });
+                        pointer: format!("{}{pointer}", place.pointer),
+                        message,
+                    },
+                };
+                let props = Value::Object(props);
+                match &edge_validator {
+                    Some(validator) => {
+                        let before = errors.len();
+                        for e in validator.iter_errors(&props) {
+                            errors
+                                .push(property_error(e.instance_path().to_string(), e.to_string()));
+                        }
+                        if errors.len() > before {
+                            continue 'places;
+                        }
+                    }
+                    None if props.as_object().is_some_and(|x| !x.is_empty()) => {
+                        errors.push(property_error(
+                            String::new(),
+                            "the foreign key has no edge schema, references can not carry properties"
+                                .to_owned(),
+                        ));
+                        continue 'places;
+                    }
+                    None => {}
                 }
-            };
 
-            if let Some(k) = &key.foreign_key_kind
-                && k.as_str() != fk_kind
-            {
-                return Err(DawnStoreError::ObjectValidationWrongForeignKeyEntryKind {
-                    api_version: api_version.to_owned(),
-                    kind: kind.to_owned(),
-                    name: obj.name.clone(),
-                    foreign_key_path: key.key_path.clone(),
-                    foreign_key_type: key.r#type.clone(),
-                    value: fk_val.clone(),
-                });
+                // one relation per referenced object, even if referenced from many places,
+                // the properties are the ones of the first place
+                if !fks.contains(&fk_string_id) {
+                    edges.insert(
+                        (key.id, fk_string_id.clone()),
+                        Edge {
+                            pointer: place.pointer.clone(),
+                            properties: props
+                                .as_object()
+                                .is_some_and(|x| !x.is_empty())
+                                .then_some(props),
+                        },
+                    );
+                    fks.push(fk_string_id);
+                }
             }
-
-            fks.push(format!("{ns}/{fk_kind}/{fk_name}"));
         }
 
         fk_string_ids.push((fks, key.id));
     }
 
-    Ok(fk_string_ids)
+    if !errors.is_empty() {
+        return Err(DawnStoreError::ValidationFailed(errors));
+    }
+    Ok((fk_string_ids, edges))
+}
+
+/// the reference and the properties of a foreign key entry, an entry is a
+/// string or an object `{"ref": "...", ...properties}`
+pub fn split_reference(value: &Value) -> Option<(&str, Map<String, Value>)> {
+    match value {
+        Value::String(x) => Some((x, Map::new())),
+        Value::Object(x) => {
+            let reference = x.get("ref")?.as_str()?;
+            let properties = x
+                .iter()
+                .filter(|(k, _)| k.as_str() != "ref")
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            Some((reference, properties))
+        }
+        _ => None,
+    }
+}
+
+/// the kind of the referenced object is already checked, the allowed kinds
+/// only restrict the api version if every entry of the kind names one
+pub fn allows_api_version(key: &ForeignKeyConstraint, target: &str, api_version: &str) -> bool {
+    let target_kind = target.split('/').nth(1).unwrap_or_default();
+    let mut versions = key
+        .allowed_kinds
+        .iter()
+        .filter(|x| x.kind == target_kind)
+        .map(|x| x.api_version.as_deref())
+        .peekable();
+    versions.peek().is_none() || versions.any(|x| x.is_none_or(|x| x == api_version))
+}
+
+/// kind and string id of a foreign key value, missing parts are the ones of
+/// the referencing object
+pub fn resolve_foreign_key<'a>(
+    value: &'a str,
+    ns: &str,
+    kind: &'a str,
+) -> Option<(&'a str, String)> {
+    let comps = value.split("/").collect::<Vec<_>>();
+    let (ns, fk_kind, fk_name) = match comps.as_slice() {
+        [ns, kind, name] => (*ns, *kind, *name),
+        // assume same ns as the current object
+        [kind, name] => (ns, *kind, *name),
+        // assume same ns and kind as the current object
+        [name] => (ns, kind, *name),
+        _ => return None,
+    };
+    Some((fk_kind, format!("{ns}/{fk_kind}/{fk_name}")))
+}
+
+pub async fn check_owners(
+    pool: &mut PgConnection,
+    obj: &dawnstore_lib::Object<Value>,
+    ns: &str,
+    string_id: &str,
+) -> Result<(), DawnStoreError> {
+    let Some(owners) = &obj.owners else {
+        return Ok(());
+    };
+    let ids = owners.iter().map(|x| x.id).collect::<Vec<_>>();
+    let existing = queries::get_objects(pool, ids.as_slice()).await?;
+    for owner in owners {
+        let owner_id = format!(
+            "{}/{}/{} ({})",
+            owner.api_version, owner.kind, owner.name, owner.id
+        );
+        let Some(x) = existing.iter().find(|x| {
+            x.id == owner.id
+                && x.api_version == owner.api_version
+                && x.kind == owner.kind
+                && x.name == owner.name
+        }) else {
+            return Err(DawnStoreError::OwnerNotFound {
+                object: string_id.to_owned(),
+                owner: owner_id,
+            });
+        };
+        if x.namespace != ns {
+            return Err(DawnStoreError::OwnerInDifferentNamespace {
+                object: string_id.to_owned(),
+                owner: owner_id,
+            });
+        }
+    }
+    Ok(())
 }
 
 pub async fn maintain_objects(
     con: &mut PgConnection,
+    sc: &RwLock<HashMap<String, KindSchema>>,
     object_infos: &HashMap<String, ObjectInfo>,
     input_objects_with_string_id: Vec<(String, dawnstore_lib::Object<Value>)>,
 ) -> Result<Vec<Object>, DawnStoreError> {
+    let schema_cache = sc.read().await;
     let mut database_objects = Vec::<Object>::with_capacity(input_objects_with_string_id.len());
     for (string_id, obj) in input_objects_with_string_id {
         let oi = object_infos.get(&string_id);
@@ -238,21 +630,160 @@ pub async fn maintain_objects(
             Some(oi) => (oi.id, oi.created_at),
             None => (uuid::Uuid::new_v4(), Utc::now()),
         };
+        // left out finalizers are kept, controllers set them and `[]` removes them
+        let finalizers = obj
+            .finalizers
+            .or_else(|| oi.map(|x| x.finalizers.clone()))
+            .unwrap_or_default();
+        let api_version = obj.api_version.unwrap();
+        let kind = obj.kind.unwrap();
+        let ttl = schema_cache
+            .get(&format!("{api_version}/{kind}"))
+            .and_then(|x| x.ttl);
+        // an expiry set before outlives applies without one, null removes it
+        let expires_at = match obj.expires_at {
+            Some(x) => x,
+            None => oi.and_then(|x| x.expires_at),
+        };
+        // the ttl is the last resort, counted from now for objects which
+        // existed before the kind got its ttl
+        let expires_at = expires_at.or(ttl.map(|x| Utc::now() + x));
+        // an object which is being deleted can only lose finalizers
+        if let Some(oi) = oi
+            && oi.deletion_timestamp.is_some()
+            && let Some(x) = finalizers.iter().find(|x| !oi.finalizers.contains(x))
+        {
+            return Err(DawnStoreError::FinalizerAddedDuringDeletion {
+                object: string_id,
+                finalizer: x.clone(),
+            });
+        }
         let new_obj = Object {
             id,
             string_id,
-            api_version: obj.api_version.unwrap(),
+            api_version,
             name: obj.name,
-            kind: obj.kind.unwrap(),
+            kind,
             created_at,
             updated_at: Utc::now(),
             namespace: obj.namespace.unwrap_or("default".to_string()),
             annotations: sqlx::types::Json(obj.annotations.unwrap_or_default()),
             labels: sqlx::types::Json(obj.labels.unwrap_or_default()),
             spec: sqlx::types::Json(obj.spec),
+            // apply never touches the status, keep what is stored
+            status: oi.and_then(|x| x.status.clone()),
+            finalizers,
+            // like the finalizers, `[]` removes the owners
+            owners: sqlx::types::Json(
+                obj.owners
+                    .or_else(|| oi.map(|x| x.owners.0.clone()))
+                    .unwrap_or_default(),
+            ),
+            expires_at,
+            deletion_timestamp: oi.and_then(|x| x.deletion_timestamp),
+            managed_fields: sqlx::types::Json(obj.managed_fields.unwrap_or_default()),
+            // set by the database
+            resource_version: 0,
         };
         database_objects.push(new_obj);
     }
-    queries::insert_or_update_multiple_objects(con, &database_objects).await?;
+    let versions = queries::insert_or_update_multiple_objects(con, &database_objects)
+        .await?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    database_objects.iter_mut().for_each(|x| {
+        x.resource_version = versions.get(&x.id).copied().unwrap_or_default();
+    });
     Ok(database_objects)
 }
+
+fn input_document(obj: &ObjectAny) -> Result<Value, DawnStoreError> {
+    Ok(serde_json::to_value(ObjectDocument {
+        labels: obj.labels.clone().unwrap_or_default(),
+        annotations: obj.annotations.clone().unwrap_or_default(),
+        spec: obj.spec.clone(),
+    })?)
+}
+
+fn stored_document(obj: &Object) -> Result<Value, DawnStoreError> {
+    Ok(serde_json::to_value(ObjectDocument {
+        labels: obj.labels.0.clone(),
+        annotations: obj.annotations.0.clone(),
+        spec: obj.spec.0.clone(),
+    })?)
+}
+
+/// locks the stored versions of the input objects and records which field
+/// manager set which fields, for server-side apply the input objects are
+/// merged into the stored ones. the locked objects are returned by string id
+pub async fn merge_with_stored_objects(
+    con: &mut PgConnection,
+    input_objects: Vec<ObjectAny>,
+    options: &ApplyOptions,
+) -> Result<(Vec<ObjectAny>, HashMap<String, Object>), DawnStoreError> {
+    let manager = match (&options.field_manager, options.server_side) {
+        (Some(x), _) => x.as_str(),
+        (None, false) => DEFAULT_APPLY_FIELD_MANAGER,
+        (None, true) => return Err(DawnStoreError::FieldManagerRequired),
+    };
+    let string_id = |obj: &ObjectAny| {
+        let ns = obj.namespace.as_deref().unwrap_or("default");
+        obj.kind
+            .as_ref()
+            .map(|kind| format!("{}/{}/{}", ns, kind, obj.name))
+    };
+    let string_ids = input_objects
+        .iter()
+        .filter_map(string_id)
+        .collect::<Vec<_>>();
+    let stored = queries::lock_objects_by_string_ids(con, string_ids.as_slice())
+        .await?
+        .into_iter()
+        .map(|x| (x.string_id.clone(), x))
+        .collect::<HashMap<String, Object>>();
+
+    let mut objects = Vec::with_capacity(input_objects.len());
+    for mut obj in input_objects {
+        // a missing kind is reported by the validation
+        let stored = string_id(&obj).and_then(|x| stored.get(&x));
+        let stored_doc = stored.map(stored_document).transpose()?;
+        let stored_managed = stored
+            .map(|x| x.managed_fields.0.clone())
+            .unwrap_or_default();
+        let input_doc = input_document(&obj)?;
+
+        let managed = if options.server_side {
+            let (merged, managed) = merge::server_side_apply(
+                stored_doc
+                    .as_ref()
+                    .unwrap_or(&Value::Object(Default::default())),
+                &stored_managed,
+                &input_doc,
+                manager,
+                options.force_conflicts,
+            )
+            .map_err(|conflicts| DawnStoreError::FieldManagerConflict {
+                object: string_id(&obj).unwrap_or_else(|| obj.name.clone()),
+                conflicts: conflicts
+                    .into_iter()
+                    .map(|x| format!("{} ({})", x.path, x.manager))
+                    .collect(),
+            })?;
+            let merged = serde_json::from_value::<ObjectDocument>(merged)?;
+            obj.labels = Some(merged.labels);
+            obj.annotations = Some(merged.annotations);
+            obj.spec = merged.spec;
+            // metadata left out of the manifest stays as stored
+            if let Some(stored) = stored {
+                obj.finalizers = obj.finalizers.or(Some(stored.finalizers.clone()));
+                obj.owners = obj.owners.or(Some(stored.owners.0.clone()));
+            }
+            managed
+        } else {
+            merge::track_update(stored_doc.as_ref(), &stored_managed, &input_doc, manager)
+        };
+        obj.managed_fields = Some(managed);
+        objects.push(obj);
+    }
+    Ok((objects, stored))
+}
@@ -8,7 +8,11 @@ use sqlx::{
     },
 };
 
-use crate::models::{ForeignKeyBehaviour, ForeignKeyType};
+use crate::models::{ForeignKeyBehaviour, ForeignKeyType, SpecIndexType, UniqueScope};
+use dawnstore_lib::{
+    ForeignKind, ManagedFields, NamespacePolicy, ObjectAny, ObjectOwner, ObjectReference,
+    ReturnAny, ValidationRule,
+};
 #[derive(FromRow)]
 pub struct ForeignKeyConstraint {
     pub id: uuid::Uuid,
@@ -18,7 +22,45 @@ pub struct ForeignKeyConstraint {
     pub parent_key_path: Option<String>,
     pub r#type: ForeignKeyType,
     pub behaviour: ForeignKeyBehaviour,
-    pub foreign_key_kind: Option<String>,
+    /// empty: every kind can be referenced
+    pub allowed_kinds: Json<Vec<ForeignKind>>,
+    /// the relations of the key can not form a cycle
+    pub acyclic: bool,
+    /// None: the references carry no properties
+    pub edge_json_schema: Option<String>,
+    pub namespace_policy: Json<NamespacePolicy>,
+}
+
+#[derive(FromRow, Clone)]
+pub struct UniqueConstraintRow {
+    pub id: uuid::Uuid,
+    pub api_version: String,
+    pub kind: String,
+    pub name: String,
+    pub paths: Vec<String>,
+    pub scope: UniqueScope,
+    pub foreign_key_path: Option<String>,
+}
+
+impl UniqueConstraintRow {
+    pub fn index_name(&self) -> String {
+        format!("uq_{}", self.id.simple())
+    }
+}
+
+#[derive(FromRow)]
+pub struct SpecIndexRow {
+    pub id: uuid::Uuid,
+    pub api_version: String,
+    pub kind: String,
+    pub path: String,
+    pub r#type: SpecIndexType,
+}
+
+impl SpecIndexRow {
+    pub fn index_name(&self) -> String {
+        format!("ix_{}", self.id.simple())
+    }
 }
 
 #[derive(FromRow)]
@@ -28,6 +70,10 @@ pub struct ObjectSchema {
     pub kind: String,
     pub aliases: Vec<String>,
     pub json_schema: String,
+    pub status_json_schema: Option<String>,
+    pub ttl_seconds: Option<i64>,
+    pub immutable: bool,
+    pub validation_rules: Json<Vec<ValidationRule>>,
 }
 
 #[derive(FromRow, serde::Serialize, serde::Deserialize, Debug)]
@@ -43,13 +89,100 @@ pub struct Object {
     pub annotations: Json<BTreeMap<String, String>>,
     pub labels: Json<BTreeMap<String, String>>,
     pub spec: Json<serde_json::Value>,
+    pub status: Option<Json<serde_json::Value>>,
+    pub finalizers: Vec<String>,
+    pub owners: Json<Vec<ObjectOwner>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub deletion_timestamp: Option<DateTime<Utc>>,
+    pub managed_fields: Json<ManagedFields>,
+    pub resource_version: i64,
+}
+
+impl Object {
+    pub fn reference(&self) -> ObjectReference {
+        ObjectReference {
+            namespace: self.namespace.clone(),
+            api_version: Some(self.api_version.clone()),
+            kind: self.kind.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl From<Object> for ReturnAny {
+    fn from(x: Object) -> Self {
+        ReturnAny {
+            id: x.id,
+            namespace: x.namespace,
+            api_version: x.api_version,
+            kind: x.kind,
+            name: x.name,
+            created_at: x.created_at,
+            updated_at: x.updated_at,
+            annotations: Some(x.annotations.0),
+            labels: Some(x.labels.0),
+            finalizers: x.finalizers,
+            owners: x.owners.0,
+            expires_at: x.expires_at,
+            deletion_timestamp: x.deletion_timestamp,
+            managed_fields: x.managed_fields.0,
+            resource_version: x.resource_version,
+            status: x.status.map(|x| x.0),
+            spec: x.spec.0,
+        }
+    }
+}
+
+/// the stored object in the shape of an applied one, e.g. to validate it again
+impl From<&Object> for ObjectAny {
+    fn from(x: &Object) -> Self {
+        ObjectAny {
+            id: Some(x.id),
+            created_at: Some(x.created_at),
+            updated_at: Some(x.updated_at),
+            annotations: Some(x.annotations.0.clone()),
+            labels: Some(x.labels.0.clone()),
+            finalizers: Some(x.finalizers.clone()),
+            owners: Some(x.owners.0.clone()),
+            expires_at: Some(x.expires_at),
+            deletion_timestamp: x.deletion_timestamp,
+            managed_fields: Some(x.managed_fields.0.clone()),
+            resource_version: Some(x.resource_version),
+            namespace: Some(x.namespace.clone()),
+            api_version: Some(x.api_version.clone()),
+            kind: Some(x.kind.clone()),
+            name: x.name.clone(),
+            status: x.status.as_ref().map(|x| x.0.clone()),
+            spec: x.spec.0.clone(),
+        }
+    }
+}
+
+/// an object whose string id is not made of its namespace, kind and name
+#[derive(FromRow, Debug)]
+pub struct StringIdMismatch {
+    pub id: Uuid,
+    pub string_id: String,
+    pub expected: String,
+}
+
+#[derive(FromRow, Debug)]
+pub struct ObjectName {
+    pub id: Uuid,
+    pub string_id: String,
 }
 
 #[derive(FromRow, serde::Serialize, serde::Deserialize)]
 pub struct ObjectInfo {
     pub id: Uuid,
     pub string_id: String,
+    pub api_version: String,
     pub created_at: DateTime<Utc>,
+    pub status: Option<Json<serde_json::Value>>,
+    pub finalizers: Vec<String>,
+    pub owners: Json<Vec<ObjectOwner>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub deletion_timestamp: Option<DateTime<Utc>>,
 }
 
 #[derive(FromRow, serde::Serialize, serde::Deserialize, Debug)]
@@ -57,6 +190,35 @@ pub struct Relation {
     pub object_id: Uuid,
     pub foreign_object_id: Uuid,
     pub foreign_key_id: Uuid,
+    /// None: the reference is a plain string
+    pub properties: Option<Json<serde_json::Value>>,
+}
+
+/// a relation reached by a traversal
+#[derive(FromRow, Debug)]
+pub struct RelationEdgeRow {
+    pub object_id: Uuid,
+    pub foreign_object_id: Uuid,
+    pub key_path: String,
+    pub properties: Option<Json<serde_json::Value>>,
+    pub depth: i32,
+}
+
+#[derive(FromRow, Debug)]
+pub struct BlockingReference {
+    pub blocked_id: Uuid,
+    pub referenced_by: String,
+}
+
+#[derive(FromRow)]
+pub struct SearchHitRow {
+    pub namespace: String,
+    pub id: Uuid,
+    pub api_version: String,
+    pub kind: String,
+    pub name: String,
+    pub rank: f32,
+    pub headline: String,
 }
 
 #[derive(FromRow, serde::Deserialize, serde::Serialize)]
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    backends::postgres::{data_models::Object, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+/// objects without finalizers are removed right away together with their
+/// relations, the others only get a deletion timestamp and stay until their
+/// last finalizer is removed
+pub async fn delete_objects(
+    con: &mut PgConnection,
+    objects: &[Object],
+) -> Result<(), DawnStoreError> {
+    let (marked, deleted): (Vec<&Object>, Vec<&Object>) =
+        objects.iter().partition(|x| !x.finalizers.is_empty());
+    let marked = marked.iter().map(|x| x.id).collect::<Vec<_>>();
+    let deleted = deleted.iter().map(|x| x.id).collect::<Vec<_>>();
+    queries::mark_objects_for_deletion(&mut *con, marked.as_slice()).await?;
+    queries::delete_relations_of_objects(&mut *con, deleted.as_slice()).await?;
+    queries::delete_objects(&mut *con, deleted.as_slice()).await?;
+    Ok(())
+}
+
+/// removes the objects which are marked for deletion and have no finalizers left
+pub async fn delete_finalized_objects(
+    con: &mut PgConnection,
+    objects: &[Object],
+) -> Result<(), DawnStoreError> {
+    let finalized = objects
+        .iter()
+        .filter(|x| x.deletion_timestamp.is_some() && x.finalizers.is_empty())
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    queries::delete_relations_of_objects(&mut *con, finalized.as_slice()).await?;
+    queries::delete_objects(con, finalized.as_slice()).await?;
+    Ok(())
+}
+
+/// the objects which are still needed by other objects through a required
+/// foreign key, mapped to the string ids of the referencing objects
+pub async fn get_blocking_references(
+    con: &mut PgConnection,
+    objects: &[Object],
+) -> Result<HashMap<Uuid, Vec<String>>, DawnStoreError> {
+    let ids = objects.iter().map(|x| x.id).collect::<Vec<_>>();
+    let mut blocked = HashMap::<Uuid, Vec<String>>::new();
+    for r in queries::get_blocking_references(con, ids.as_slice()).await? {
+        blocked
+            .entry(r.blocked_id)
+            .or_default()
+            .push(r.referenced_by);
+    }
+    Ok(blocked)
+}
+
+/// deletes the objects matched by the prune selector of the options which
+/// were not just applied, objects still required by others block the prune
+pub async fn prune_objects(
+    con: &mut PgConnection,
+    applied: &[Object],
+    options: &ApplyOptions,
+) -> Result<Vec<Object>, DawnStoreError> {
+    let mut labels = match &options.selector {
+        Some(x) => parse_label_selector(x)
+            .ok_or_else(|| DawnStoreError::InvalidLabelSelector(x.clone()))?,
+        None => Default::default(),
+    };
+    if let Some(x) = &options.applied_set {
+        labels.insert(APPLIED_SET_LABEL.to_string(), x.clone());
+    }
+    if labels.is_empty() {
+        return Err(DawnStoreError::PruneWithoutSelector);
+    }
+    let applied = applied
+        .iter()
+        .map(|x| x.string_id.clone())
+        .collect::<Vec<_>>();
+    let pruned = queries::get_objects_by_labels_excluding(
+        &mut *con,
+        &serde_json::to_value(labels)?,
+        applied.as_slice(),
+    )
+    .await?;
+    let mut blocked = get_blocking_references(&mut *con, &pruned).await?;
+    if let Some(obj) = pruned.iter().find(|x| blocked.contains_key(&x.id)) {
+        return Err(DawnStoreError::DeletionBlockedByForeignKey {
+            object: obj.string_id.clone(),
+            referenced_by: blocked.remove(&obj.id).unwrap_or_default(),
+        });
+    }
+    delete_objects(con, pruned.as_slice()).await?;
+    Ok(pruned)
+}
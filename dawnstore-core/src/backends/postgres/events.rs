@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::{
+    backends::postgres::{PostgresBackend, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+/// events are cleaned up by the expiry reaper after this long without a repetition
+pub const EVENT_TTL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_EVENT_LIMIT: usize = 50;
+
+/// stable name of an event, so repeated events end up in the same object,
+/// the message is left out as it may differ between repetitions
+fn event_name(event: &RecordEvent) -> String {
+    // fnv-1a, the std hashers are not guaranteed to be stable across releases
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let event_type = format!("{:?}", event.event_type);
+    for part in [event_type.as_str(), &event.reason] {
+        for b in part.bytes().chain([0]) {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    let obj = &event.involved_object;
+    format!("{}.{}.{hash:016x}", obj.kind, obj.name)
+}
+
+impl PostgresBackend {
+    /// records an event about an object, an event with the same type and reason
+    /// of the same object only increases its count and updates last_seen and
+    /// the message
+    pub async fn record_event(&self, event: &RecordEvent) -> Result<ReturnAny, DawnStoreError> {
+        let spec = serde_json::json!({
+            "involved_object": event.involved_object,
+            "event_type": event.event_type,
+            "reason": event.reason,
+            "message": event.message,
+        });
+        let ns = &event.involved_object.namespace;
+        let name = event_name(event);
+        let mut con = self.pool.acquire().await?;
+        let obj = queries::upsert_event(
+            con.as_mut(),
+            Uuid::new_v4(),
+            ns,
+            &name,
+            &spec,
+            EVENT_TTL.as_secs() as i64,
+        )
+        .await?;
+        match obj {
+            Some(x) => Ok(x.into()),
+            None => {
+                let string_id = format!("{ns}/{EVENT_KIND}/{name}");
+                let stored = queries::get_object_by_string_id(con.as_mut(), &string_id).await?;
+                Err(DawnStoreError::BuiltInObjectTaken {
+                    object: string_id,
+                    api_version: stored
+                        .as_ref()
+                        .map(|x| x.api_version.clone())
+                        .unwrap_or_default(),
+                    kind: stored.map(|x| x.kind).unwrap_or_default(),
+                })
+            }
+        }
+    }
+
+    /// the most recent events of an object
+    pub async fn get_events(
+        &self,
+        filter: &GetEventsFilter,
+    ) -> Result<Vec<ReturnAny>, DawnStoreError> {
+        let ns = filter.namespace.as_deref().unwrap_or("default");
+        let limit = filter.limit.unwrap_or(DEFAULT_EVENT_LIMIT) as i64;
+        let mut con = self.pool.acquire().await?;
+        let events =
+            queries::get_events_of_object(con.as_mut(), ns, &filter.kind, &filter.name, limit)
+                .await?;
+        Ok(events.into_iter().map(ReturnAny::from).collect())
+    }
+
+    /// records an event emitted by the store itself, failures are only logged
+    /// so they never mask the operation which caused the event
+    pub(crate) async fn emit_event(
+        &self,
+        involved_object: ObjectReference,
+        event_type: EventType,
+        reason: &str,
+        message: impl Into<String>,
+    ) {
+        let event = RecordEvent {
+            involved_object,
+            event_type,
+            reason: reason.to_owned(),
+            message: message.into(),
+        };
+        self.emit_events(vec![event]).await;
+    }
+
+    /// records the events raised during a transaction once it is over, so
+    /// they never need a second connection while the transaction holds one
+    pub(crate) async fn emit_events(&self, events: Vec<RecordEvent>) {
+        for event in events {
+            if let Err(e) = self.record_event(&event).await {
+                tracing::error!("recording event {} failed: {e}", event.reason);
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::{Connection, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    backends::postgres::{
+        PostgresBackend, apply_impl,
+        data_models::{Object, Relation},
+        queries,
+    },
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+const FSCK_BATCH_SIZE: i64 = 500;
+
+fn issue(kind: FsckIssueKind, object: Option<&str>, message: String, repaired: bool) -> FsckIssue {
+    FsckIssue {
+        kind,
+        object: object.map(|x| x.to_owned()),
+        message,
+        repaired,
+    }
+}
+
+/// the validation errors of an object as issues, everything else aborts the check
+fn schema_issues(object: &str, error: DawnStoreError) -> Result<Vec<FsckIssue>, DawnStoreError> {
+    let errors = match error {
+        DawnStoreError::DatabaseError(_) => return Err(error),
+        DawnStoreError::ValidationFailed(x) => x.into_iter().map(|x| x.error).collect(),
+        e => vec![e],
+    };
+    Ok(errors
+        .into_iter()
+        .map(|e| {
+            issue(
+                FsckIssueKind::SchemaViolation,
+                Some(object),
+                e.to_string(),
+                false,
+            )
+        })
+        .collect())
+}
+
+fn same_relation(a: &Relation, b: &Relation) -> bool {
+    a.object_id == b.object_id
+        && a.foreign_object_id == b.foreign_object_id
+        && a.foreign_key_id == b.foreign_key_id
+}
+
+async fn delete_relations(
+    con: &mut sqlx::PgConnection,
+    relations: &[Relation],
+) -> Result<(), DawnStoreError> {
+    let object_ids = relations.iter().map(|x| x.object_id).collect::<Vec<_>>();
+    let foreign_object_ids = relations
+        .iter()
+        .map(|x| x.foreign_object_id)
+        .collect::<Vec<_>>();
+    let foreign_key_ids = relations
+        .iter()
+        .map(|x| x.foreign_key_id)
+        .collect::<Vec<_>>();
+    queries::delete_multiple_relations(
+        con,
+        object_ids.as_slice(),
+        foreign_object_ids.as_slice(),
+        foreign_key_ids.as_slice(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// a savepoint, so a string id taken concurrently only fails this repair
+async fn repair_string_id(
+    con: &mut sqlx::PgConnection,
+    id: Uuid,
+    string_id: &str,
+) -> Result<bool, DawnStoreError> {
+    let mut savepoint = con.begin().await?;
+    match queries::update_string_id(savepoint.as_mut(), id, string_id).await {
+        Ok(repaired) => {
+            savepoint.commit().await?;
+            Ok(repaired)
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl PostgresBackend {
+    /// compares the objects with their relations and the current definition
+    /// of their kinds. a repair rebuilds the relations from the specs and
+    /// fixes string ids, specs are never changed. the objects are checked
+    /// in batches, each in its own transaction
+    pub async fn fsck(&self, options: &FsckOptions) -> Result<FsckReport, DawnStoreError> {
+        let mut con = self.pool.begin().await?;
+        let mut issues = Vec::new();
+
+        // string ids first, the references are resolved through them
+        for x in queries::get_string_id_mismatches(con.as_mut()).await? {
+            let repaired =
+                options.repair && repair_string_id(con.as_mut(), x.id, &x.expected).await?;
+            let message = match options.repair && !repaired {
+                true => format!(
+                    "string id is {}, {} is used by another object",
+                    x.string_id, x.expected
+                ),
+                false => format!("string id is {}", x.string_id),
+            };
+            issues.push(issue(
+                FsckIssueKind::StringIdMismatch,
+                Some(&x.expected),
+                message,
+                repaired,
+            ));
+        }
+
+        let dangling = queries::get_dangling_relations(con.as_mut()).await?;
+        for x in &dangling {
+            issues.push(issue(
+                FsckIssueKind::DanglingRelation,
+                None,
+                format!(
+                    "relation {} -> {} of foreign key {}",
+                    x.object_id, x.foreign_object_id, x.foreign_key_id
+                ),
+                options.repair,
+            ));
+        }
+        if options.repair {
+            delete_relations(con.as_mut(), dangling.as_slice()).await?;
+        }
+        con.commit().await?;
+
+        let mut scanned_objects = 0;
+        let mut after = Uuid::nil();
+        loop {
+            let mut con = self.pool.begin().await?;
+            let objects = queries::get_objects_after(con.as_mut(), after, FSCK_BATCH_SIZE).await?;
+            let Some(last) = objects.last() else {
+                break;
+            };
+            after = last.id;
+            scanned_objects += objects.len();
+            self.check_objects(con.as_mut(), &objects, &dangling, options, &mut issues)
+                .await?;
+            if options.repair {
+                con.commit().await?;
+            }
+        }
+
+        Ok(FsckReport {
+            scanned_objects,
+            issues,
+        })
+    }
+
+    /// checks the relations and schemas of a batch of objects
+    async fn check_objects(
+        &self,
+        con: &mut sqlx::PgConnection,
+        objects: &[Object],
+        dangling: &[Relation],
+        options: &FsckOptions,
+        issues: &mut Vec<FsckIssue>,
+    ) -> Result<(), DawnStoreError> {
+        let object_ids = objects.iter().map(|x| x.id).collect::<Vec<_>>();
+        let mut stored = HashMap::<Uuid, Vec<Relation>>::new();
+        for x in queries::get_relations_of_objects(&mut *con, object_ids.as_slice()).await? {
+            // already reported as dangling
+            if dangling.iter().any(|y| same_relation(&x, y)) {
+                continue;
+            }
+            stored.entry(x.object_id).or_default().push(x);
+        }
+
+        let mut checked = Vec::new();
+        for obj in objects {
+            let input = ObjectAny::from(obj);
+            let object_id = format!("{}/{}", obj.api_version, obj.kind);
+            if let Err(e) = apply_impl::validate_object_schema(
+                &mut *con,
+                &self.schema_cache,
+                &input,
+                None,
+                &obj.api_version,
+                &obj.kind,
+                &object_id,
+            )
+            .await
+            {
+                issues.extend(schema_issues(&obj.string_id, e)?);
+            }
+            let result = apply_impl::check_foreign_keys(
+                &mut *con,
+                &self.foreign_key_cache,
+                &input,
+                &obj.api_version,
+                &obj.kind,
+                &obj.namespace,
+                object_id,
+            )
+            .await;
+            // the relations of invalid references are unknown, they stay as they are
+            match result {
+                Ok(x) => checked.push((obj, x)),
+                Err(e) => issues.extend(schema_issues(&obj.string_id, e)?),
+            }
+        }
+
+        // the referenced objects and the targets of the stored relations
+        let targets = checked
+            .iter()
+            .flat_map(|(_, (fks, _))| fks.iter().flat_map(|(x, _)| x.iter().cloned()))
+            .collect::<Vec<_>>();
+        let target_ids = stored
+            .values()
+            .flatten()
+            .map(|x| x.foreign_object_id)
+            .collect::<Vec<_>>();
+        let names =
+            queries::get_object_names(&mut *con, targets.as_slice(), target_ids.as_slice()).await?;
+        let ids = names
+            .iter()
+            .map(|x| (x.string_id.as_str(), x.id))
+            .collect::<HashMap<_, _>>();
+        let string_ids = names
+            .iter()
+            .map(|x| (x.id, x.string_id.as_str()))
+            .collect::<HashMap<_, _>>();
+
+        let mut missing = Vec::<Relation>::new();
+        let mut stale = Vec::<Relation>::new();
+        for (obj, (fks, edges)) in checked {
+            let mut expected = Vec::<Relation>::new();
+            for (targets, foreign_key_id) in fks {
+                for target in targets {
+                    let Some(foreign_object_id) = ids.get(target.as_str()) else {
+                        issues.push(issue(
+                            FsckIssueKind::DanglingReference,
+                            Some(&obj.string_id),
+                            format!("referenced object {target} does not exist"),
+                            false,
+                        ));
+                        continue;
+                    };
+                    expected.push(Relation {
+                        object_id: obj.id,
+                        foreign_object_id: *foreign_object_id,
+                        foreign_key_id,
+                        properties: edges
+                            .get(&(foreign_key_id, target.clone()))
+                            .and_then(|x| x.properties.clone())
+                            .map(Json),
+                    });
+                }
+            }
+            let existing = stored.remove(&obj.id).unwrap_or_default();
+            let expected_keys = expected
+                .iter()
+                .map(|x| (x.foreign_object_id, x.foreign_key_id))
+                .collect::<HashSet<_>>();
+            for x in expected {
+                let target = string_ids
+                    .get(&x.foreign_object_id)
+                    .copied()
+                    .unwrap_or_default();
+                match existing.iter().find(|y| same_relation(&x, y)) {
+                    None => issues.push(issue(
+                        FsckIssueKind::MissingRelation,
+                        Some(&obj.string_id),
+                        format!("no relation to {target}"),
+                        options.repair,
+                    )),
+                    Some(y)
+                        if y.properties.as_ref().map(|x| &x.0)
+                            != x.properties.as_ref().map(|x| &x.0) =>
+                    {
+                        issues.push(issue(
+                            FsckIssueKind::RelationPropertiesMismatch,
+                            Some(&obj.string_id),
+                            format!("relation to {target} has other properties than the spec"),
+                            options.repair,
+                        ))
+                    }
+                    Some(_) => continue,
+                }
+                missing.push(x);
+            }
+            for y in existing {
+                if expected_keys.contains(&(y.foreign_object_id, y.foreign_key_id)) {
+                    continue;
+                }
+                let target = string_ids
+                    .get(&y.foreign_object_id)
+                    .copied()
+                    .unwrap_or_default();
+                issues.push(issue(
+                    FsckIssueKind::StaleRelation,
+                    Some(&obj.string_id),
+                    format!("relation to {target} is not in the spec"),
+                    options.repair,
+                ));
+                stale.push(y);
+            }
+        }
+
+        if options.repair {
+            delete_relations(&mut *con, stale.as_slice()).await?;
+            // updates the properties of existing relations as well
+            queries::insert_multiple_relation(&mut *con, missing.as_slice()).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+
+use crate::{
+    backends::postgres::{PostgresBackend, delete_impl, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+impl PostgresBackend {
+    /// deletes all objects whose owners are gone and finishes foreground deletions
+    /// whose dependents are all deleted
+    pub async fn collect_garbage(&self) -> Result<(), DawnStoreError> {
+        let mut con = self.pool.begin().await?;
+        let orphans = queries::get_orphaned_objects(con.as_mut()).await?;
+        let blocked = delete_impl::get_blocking_references(con.as_mut(), &orphans).await?;
+        let (blocked_orphans, orphans): (Vec<_>, Vec<_>) = orphans
+            .into_iter()
+            .partition(|x| blocked.contains_key(&x.id));
+        if !orphans.is_empty() {
+            tracing::info!("garbage collecting {} objects", orphans.len());
+        }
+        delete_impl::delete_objects(con.as_mut(), orphans.as_slice()).await?;
+
+        for id in queries::get_finished_foreground_deletions(con.as_mut()).await? {
+            queries::remove_finalizer(con.as_mut(), id, FOREGROUND_DELETION_FINALIZER).await?;
+            let obj = queries::get_object(con.as_mut(), id).await?;
+            delete_impl::delete_finalized_objects(con.as_mut(), obj.as_slice()).await?;
+        }
+        con.commit().await?;
+
+        for obj in &orphans {
+            self.emit_event(
+                obj.reference(),
+                EventType::Normal,
+                "GarbageCollected",
+                "all owners are deleted",
+            )
+            .await;
+        }
+        for obj in &blocked_orphans {
+            let referenced_by = blocked.get(&obj.id).cloned().unwrap_or_default();
+            self.emit_event(
+                obj.reference(),
+                EventType::Warning,
+                "DeletionBlocked",
+                format!(
+                    "all owners are deleted but still referenced by {}",
+                    referenced_by.join(", ")
+                ),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    pub fn spawn_garbage_collector(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = backend.collect_garbage().await {
+                    tracing::error!("garbage collection failed: {e}");
+                }
+            }
+        })
+    }
+}
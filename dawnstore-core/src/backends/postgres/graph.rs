@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::{
+    backends::postgres::{PostgresBackend, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+impl PostgresBackend {
+    /// the objects and relations reached from an object along a foreign key,
+    /// objects reached over several paths are returned once
+    pub async fn traverse(
+        &self,
+        filter: &TraverseFilter,
+    ) -> Result<TraverseResult, DawnStoreError> {
+        let ns = filter.namespace.as_deref().unwrap_or("default");
+        let string_id = format!("{}/{}/{}", ns, filter.kind, filter.name);
+        let depth = filter
+            .depth
+            .unwrap_or(MAX_TRAVERSE_DEPTH)
+            .min(MAX_TRAVERSE_DEPTH) as i32;
+        let mut con = self.pool.acquire().await?;
+        let Some(start) = queries::get_object_by_string_id(con.as_mut(), &string_id).await? else {
+            return Err(DawnStoreError::ObjectNotFound(string_id));
+        };
+        let key_path = filter.foreign_key.as_deref();
+        let edges = match filter.direction {
+            TraverseDirection::Ancestors => {
+                queries::get_ancestor_edges(con.as_mut(), start.id, key_path, depth).await?
+            }
+            TraverseDirection::Descendants => {
+                queries::get_descendant_edges(con.as_mut(), start.id, key_path, depth).await?
+            }
+        };
+
+        // reached objects in the order of the edges, so by depth
+        let mut ids = Vec::new();
+        for edge in &edges {
+            for id in [edge.object_id, edge.foreign_object_id] {
+                if id != start.id && !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        let mut reached = queries::get_objects(con.as_mut(), ids.as_slice())
+            .await?
+            .into_iter()
+            .map(|x| (x.id, x))
+            .collect::<HashMap<_, _>>();
+        let mut string_ids = reached
+            .values()
+            .map(|x| (x.id, format!("{}/{}/{}", x.namespace, x.kind, x.name)))
+            .collect::<HashMap<_, _>>();
+        string_ids.insert(start.id, string_id);
+
+        let edges = edges
+            .into_iter()
+            .filter_map(|x| {
+                Some(RelationEdge {
+                    object: string_ids.get(&x.object_id)?.clone(),
+                    foreign_object: string_ids.get(&x.foreign_object_id)?.clone(),
+                    foreign_key: x.key_path,
+                    properties: x.properties.map(|x| x.0),
+                    depth: x.depth as u32,
+                })
+            })
+            .collect();
+        let mut objects = vec![start.into()];
+        objects.extend(
+            ids.iter()
+                .filter_map(|x| reached.remove(x))
+                .map(ReturnAny::from),
+        );
+        Ok(TraverseResult { objects, edges })
+    }
+}
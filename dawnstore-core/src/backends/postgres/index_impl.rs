@@ -0,0 +1,82 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    backends::postgres::{data_models::SpecIndexRow, queries},
+    error::DawnStoreError,
+    models::{SpecIndex, SpecIndexType},
+};
+
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// spec paths end up in index definitions and queries, so only plain
+/// segments are allowed
+pub fn is_plain_path(path: &str) -> bool {
+    path.split('.').all(|seg| {
+        !seg.is_empty()
+            && seg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+/// the text array literal of a plain spec path, used with `#>` and `#>>`
+pub fn path_literal(path: &str) -> String {
+    format!("'{{{}}}'", path.replace('.', ","))
+}
+
+/// btree indexes compare the text of a value like the field selectors do,
+/// gin indexes the jsonb value for containment
+fn index_definition(row: &SpecIndexRow) -> String {
+    let path = path_literal(&row.path);
+    let (method, expression) = match row.r#type {
+        SpecIndexType::BTree => ("BTREE", format!("(spec #>> {path})")),
+        SpecIndexType::Gin => ("GIN", format!("(spec #> {path})")),
+    };
+    format!(
+        "CREATE INDEX IF NOT EXISTS {} ON objects USING {method} ({expression}) WHERE kind = {}",
+        row.index_name(),
+        quote_literal(&row.kind),
+    )
+}
+
+/// brings the registered spec indexes of a kind in line with the declared ones
+pub async fn sync_spec_indexes(
+    con: &mut PgConnection,
+    api_version: &str,
+    kind: &str,
+    indexes: &[SpecIndex],
+) -> Result<(), DawnStoreError> {
+    let existing = queries::get_spec_indexes(&mut *con, api_version, kind).await?;
+    let matches = |row: &SpecIndexRow, x: &SpecIndex| row.path == x.path && row.r#type == x.ty;
+    for row in &existing {
+        if !indexes.iter().any(|x| matches(row, x)) {
+            queries::execute_ddl(
+                &mut *con,
+                &format!("DROP INDEX IF EXISTS {}", row.index_name()),
+            )
+            .await?;
+            queries::delete_spec_index(&mut *con, row.id).await?;
+        }
+    }
+    for x in indexes {
+        if existing.iter().any(|row| matches(row, x)) {
+            continue;
+        }
+        if !is_plain_path(&x.path) {
+            return Err(DawnStoreError::InvalidSpecIndexPath(x.path.clone()));
+        }
+        let row = SpecIndexRow {
+            id: Uuid::new_v4(),
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+            path: x.path.clone(),
+            r#type: x.ty,
+        };
+        queries::insert_spec_index(&mut *con, &row).await?;
+        queries::execute_ddl(&mut *con, &index_definition(&row)).await?;
+    }
+    Ok(())
+}
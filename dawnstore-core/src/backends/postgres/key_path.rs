@@ -0,0 +1,240 @@
+use serde_json::{Map, Value};
+
+/// segment of a foreign key path, the path `slots[*].item` references the
+/// item of every element of the slots array
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Field(String),
+    Each,
+}
+
+/// the path has to start and end with a field, `[*]` can follow any field
+pub fn parse(path: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (field, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if field.is_empty() {
+            return None;
+        }
+        segments.push(Segment::Field(field.to_owned()));
+        while !rest.is_empty() {
+            rest = rest.strip_prefix("[*]")?;
+            segments.push(Segment::Each);
+        }
+    }
+    match segments.last() {
+        Some(Segment::Field(_)) => Some(segments),
+        _ => None,
+    }
+}
+
+/// a place of the path within a document
+pub struct Occurrence<'a> {
+    /// json pointer with the array indexes, e.g. `/slots/2/item`
+    pub pointer: String,
+    /// None: a field on the way is missing
+    pub value: Option<&'a Value>,
+}
+
+fn join(pointer: &str, segment: &str) -> String {
+    format!(
+        "{pointer}/{}",
+        segment.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+/// every place of the path, empty arrays have no places
+pub fn resolve<'a>(doc: &'a Value, segments: &[Segment]) -> Vec<Occurrence<'a>> {
+    fn walk<'a>(
+        value: Option<&'a Value>,
+        segments: &[Segment],
+        pointer: String,
+        occurrences: &mut Vec<Occurrence<'a>>,
+    ) {
+        let Some((segment, rest)) = segments.split_first() else {
+            occurrences.push(Occurrence { pointer, value });
+            return;
+        };
+        match (segment, value) {
+            (Segment::Field(field), _) => {
+                let next = value.and_then(|x| x.get(field));
+                walk(next, rest, join(&pointer, field), occurrences);
+            }
+            (Segment::Each, Some(Value::Array(items))) => {
+                for (i, item) in items.iter().enumerate() {
+                    walk(
+                        Some(item),
+                        rest,
+                        join(&pointer, &i.to_string()),
+                        occurrences,
+                    );
+                }
+            }
+            // a missing array is reported at the array
+            (Segment::Each, _) => occurrences.push(Occurrence {
+                pointer,
+                value: None,
+            }),
+        }
+    }
+    let mut occurrences = Vec::new();
+    walk(Some(doc), segments, String::new(), &mut occurrences);
+    occurrences
+}
+
+/// calls f with the object holding the last field of every place of the path,
+/// missing objects on the way are created, missing arrays are skipped
+pub fn for_each_parent_mut<E>(
+    doc: &mut Value,
+    segments: &[Segment],
+    f: &mut impl FnMut(&mut Map<String, Value>, &str) -> Result<(), E>,
+) -> Result<(), E> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return Ok(());
+    };
+    match segment {
+        Segment::Field(field) => {
+            let Value::Object(map) = doc else {
+                return Ok(());
+            };
+            if rest.is_empty() {
+                return f(map, field);
+            }
+            let next = match rest.first() {
+                Some(Segment::Each) => match map.get_mut(field) {
+                    Some(x) => x,
+                    None => return Ok(()),
+                },
+                _ => map
+                    .entry(field.clone())
+                    .or_insert_with(|| Value::Object(Default::default())),
+            };
+            for_each_parent_mut(next, rest, f)
+        }
+        Segment::Each => {
+            if let Value::Array(items) = doc {
+                for item in items {
+                    for_each_parent_mut(item, rest, f)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(x: &str) -> Segment {
+        Segment::Field(x.to_owned())
+    }
+
+    fn places(doc: &Value, path: &str) -> Vec<(String, Option<Value>)> {
+        resolve(doc, &parse(path).unwrap())
+            .into_iter()
+            .map(|x| (x.pointer, x.value.cloned()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_wildcards() {
+        assert_eq!(parse("parent"), Some(vec![field("parent")]));
+        assert_eq!(
+            parse("slots[*].item"),
+            Some(vec![field("slots"), Segment::Each, field("item")])
+        );
+        assert_eq!(
+            parse("a[*][*].b"),
+            Some(vec![field("a"), Segment::Each, Segment::Each, field("b")])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for path in [
+            "", "slots[*]", "a[*][*]", "[*].a", "a..b", "a[0].b", "a[*]x.b", "a[].b",
+        ] {
+            assert_eq!(parse(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn resolves_every_element() {
+        let doc = json!({"a": [[{"b": 1}, {"b": 2}], [{"b": 3}]]});
+        assert_eq!(
+            places(&doc, "a[*][*].b"),
+            [
+                ("/a/0/0/b".to_owned(), Some(json!(1))),
+                ("/a/0/1/b".to_owned(), Some(json!(2))),
+                ("/a/1/0/b".to_owned(), Some(json!(3))),
+            ]
+        );
+        assert!(places(&json!({"a": []}), "a[*][*].b").is_empty());
+    }
+
+    #[test]
+    fn reports_missing_fields_at_their_place() {
+        // a missing array is reported at the array, not at the fields below it
+        assert_eq!(
+            places(&json!({}), "slots[*].item"),
+            [("/slots".to_owned(), None)]
+        );
+        assert_eq!(
+            places(&json!({"slots": "x"}), "slots[*].item"),
+            [("/slots".to_owned(), None)]
+        );
+        assert_eq!(
+            places(&json!({"slots": [{}]}), "slots[*].item"),
+            [("/slots/0/item".to_owned(), None)]
+        );
+        assert_eq!(places(&json!({}), "a.b"), [("/a/b".to_owned(), None)]);
+    }
+
+    #[test]
+    fn escapes_pointer_segments() {
+        let doc = json!({"a/b": {"c~d": "x"}});
+        assert_eq!(
+            places(&doc, "a/b.c~d"),
+            [("/a~1b/c~0d".to_owned(), Some(json!("x")))]
+        );
+    }
+
+    #[test]
+    fn visits_the_parents_of_every_place() {
+        let mut doc = json!({"slots": [{"item": "a"}, {}], "other": 1});
+        let segments = parse("slots[*].item").unwrap();
+        for_each_parent_mut::<()>(&mut doc, &segments, &mut |parent, field| {
+            parent.insert(field.to_owned(), json!("x"));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            doc,
+            json!({"slots": [{"item": "x"}, {"item": "x"}], "other": 1})
+        );
+
+        // missing objects are created, missing arrays are skipped
+        let mut doc = json!({});
+        let mut visited = Vec::new();
+        for segments in [parse("a.b.c").unwrap(), parse("list[*].d").unwrap()] {
+            for_each_parent_mut::<()>(&mut doc, &segments, &mut |_, field| {
+                visited.push(field.to_owned());
+                Ok(())
+            })
+            .unwrap();
+        }
+        assert_eq!(visited, ["c"]);
+        assert_eq!(doc, json!({"a": {"b": {}}}));
+
+        // errors stop the walk
+        let mut doc = json!({"slots": [{}, {}]});
+        let mut calls = 0;
+        let result = for_each_parent_mut(&mut doc, &segments, &mut |_, _| {
+            calls += 1;
+            Err("stop")
+        });
+        assert_eq!((result, calls), (Err("stop"), 1));
+    }
+}
@@ -0,0 +1,114 @@
+use uuid::Uuid;
+
+use crate::{
+    backends::postgres::{PostgresBackend, data_models::Object, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+const DEFAULT_LEASE_DURATION_SECONDS: i64 = 15;
+
+/// leases are only written by the lease endpoints, the generic writes would
+/// skip the holder and expiry checks
+pub(crate) fn check_not_lease(
+    api_version: &str,
+    kind: &str,
+    string_id: &str,
+) -> Result<(), DawnStoreError> {
+    match api_version == LEASE_API_VERSION && kind == LEASE_KIND {
+        true => Err(DawnStoreError::LeaseNotWritable(string_id.to_owned())),
+        false => Ok(()),
+    }
+}
+
+/// a lease which is expired right away could be taken by everyone, a very
+/// long one outlives its holder
+fn check_duration(duration: i64) -> Result<i64, DawnStoreError> {
+    match (1..=MAX_LEASE_DURATION_SECONDS).contains(&duration) {
+        true => Ok(duration),
+        false => Err(DawnStoreError::InvalidLeaseDuration(duration)),
+    }
+}
+
+/// the error of a string id held by another object than a lease
+fn taken_error(obj: &Object, string_id: String) -> Option<DawnStoreError> {
+    (obj.api_version != LEASE_API_VERSION || obj.kind != LEASE_KIND).then(|| {
+        DawnStoreError::BuiltInObjectTaken {
+            object: string_id,
+            api_version: obj.api_version.clone(),
+            kind: obj.kind.clone(),
+        }
+    })
+}
+
+impl PostgresBackend {
+    /// creates the lease if needed and takes it when it is free, expired or
+    /// already held by the requesting holder
+    pub async fn acquire_lease(&self, req: &LeaseRequest) -> Result<ReturnAny, DawnStoreError> {
+        let ns = req.namespace.as_deref().unwrap_or("default");
+        let duration = check_duration(
+            req.lease_duration_seconds
+                .unwrap_or(DEFAULT_LEASE_DURATION_SECONDS),
+        )?;
+        let mut con = self.pool.acquire().await?;
+        let lease = queries::acquire_lease(
+            con.as_mut(),
+            Uuid::new_v4(),
+            ns,
+            &req.name,
+            &req.holder_identity,
+            duration,
+        )
+        .await?;
+        match lease {
+            Some(x) => Ok(x.into()),
+            None => {
+                let string_id = format!("{ns}/{LEASE_KIND}/{}", req.name);
+                let stored = queries::get_object_by_string_id(con.as_mut(), &string_id).await?;
+                if let Some(e) = stored
+                    .as_ref()
+                    .and_then(|x| taken_error(x, string_id.clone()))
+                {
+                    return Err(e);
+                }
+                let holder = stored
+                    .and_then(|x| x.spec.0.get("holder_identity").cloned())
+                    .and_then(|x| x.as_str().map(|x| x.to_owned()))
+                    .unwrap_or_default();
+                Err(DawnStoreError::LeaseHeldByOther {
+                    lease: string_id,
+                    holder,
+                })
+            }
+        }
+    }
+
+    pub async fn renew_lease(&self, req: &LeaseRequest) -> Result<ReturnAny, DawnStoreError> {
+        let ns = req.namespace.as_deref().unwrap_or("default");
+        let string_id = format!("{ns}/{LEASE_KIND}/{}", req.name);
+        let duration = req.lease_duration_seconds.map(check_duration).transpose()?;
+        let mut con = self.pool.acquire().await?;
+        match queries::renew_lease(con.as_mut(), &string_id, &req.holder_identity, duration).await?
+        {
+            Some(x) => Ok(x.into()),
+            None => Err(DawnStoreError::LeaseNotHeld {
+                lease: string_id,
+                holder: req.holder_identity.clone(),
+            }),
+        }
+    }
+
+    pub async fn release_lease(&self, req: &LeaseRequest) -> Result<ReturnAny, DawnStoreError> {
+        let ns = req.namespace.as_deref().unwrap_or("default");
+        let string_id = format!("{ns}/{LEASE_KIND}/{}", req.name);
+        let mut con = self.pool.acquire().await?;
+        match queries::release_lease(con.as_mut(), &string_id, &req.holder_identity).await? {
+            Some(x) => Ok(x.into()),
+            None => Err(DawnStoreError::LeaseNotHeld {
+                lease: string_id,
+                holder: req.holder_identity.clone(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use serde_json::Value;
+
+use crate::{
+    backends::postgres::data_models::Object, error::DawnStoreError, merge::ObjectDocument,
+};
+
+use dawnstore_lib::*;
+
+/// applies the patch to the stored object and returns the object to apply,
+/// everything outside of labels, annotations and spec stays as stored
+pub fn patch_object(
+    obj: Object,
+    patch_type: PatchType,
+    patch: &Value,
+) -> Result<ObjectAny, DawnStoreError> {
+    let mut doc = serde_json::to_value(ObjectDocument {
+        labels: obj.labels.0,
+        annotations: obj.annotations.0,
+        spec: obj.spec.0,
+    })?;
+    match patch_type {
+        PatchType::Merge => json_patch::merge(&mut doc, patch),
+        PatchType::Json => {
+            let patch = serde_json::from_value::<json_patch::Patch>(patch.clone())?;
+            json_patch::patch(&mut doc, &patch)?;
+        }
+    }
+    let doc = serde_json::from_value::<ObjectDocument>(doc)?;
+    Ok(ObjectAny {
+        id: Some(obj.id),
+        created_at: Some(obj.created_at),
+        updated_at: Some(obj.updated_at),
+        annotations: Some(doc.annotations),
+        labels: Some(doc.labels),
+        finalizers: Some(obj.finalizers),
+        owners: Some(obj.owners.0),
+        expires_at: Some(obj.expires_at),
+        deletion_timestamp: obj.deletion_timestamp,
+        managed_fields: None,
+        resource_version: None,
+        namespace: Some(obj.namespace),
+        api_version: Some(obj.api_version),
+        kind: Some(obj.kind),
+        name: obj.name,
+        status: None,
+        spec: doc.spec,
+    })
+}
@@ -1,13 +1,15 @@
 #![allow(dead_code)]
 use sqlx::{PgConnection, QueryBuilder};
 
-use crate::backends::postgres::data_models::{ApiObjectInfo, ForeignKeyConstraint, Object, ObjectInfo, ObjectSchema, Relation};
+use crate::backends::postgres::data_models::{ApiObjectInfo, BlockingReference, ForeignKeyConstraint, Object, ObjectInfo, ObjectName, ObjectSchema, Relation, RelationEdgeRow, SearchHitRow, SpecIndexRow, StringIdMismatch, UniqueConstraintRow};
+use crate::backends::postgres::index_impl::{path_literal, quote_literal};
+use crate::backends::postgres::query_impl;
 use dawnstore_lib::*;
 
 // foreign key constraint
 use sqlx::{PgPool, Result};
 use uuid::Uuid;
-use crate::models::{ForeignKeyType, ForeignKeyBehaviour};
+use crate::models::{ForeignKeyType, ForeignKeyBehaviour, SpecIndexType, UniqueScope};
 
 // Fetches a single constraint by ID
  pub async fn get_foreign_key_constraints(
@@ -23,7 +25,10 @@ use crate::models::{ForeignKeyType, ForeignKeyBehaviour};
              parent_key_path, 
              type as "type: ForeignKeyType", 
              behaviour as "behaviour: ForeignKeyBehaviour", 
-             foreign_key_kind 
+             allowed_kinds as "allowed_kinds: _",
+             acyclic,
+             edge_json_schema,
+             namespace_policy as "namespace_policy: _"
          FROM foreign_key_constraints 
          WHERE api_version = $1 and kind = $2
          "#,
@@ -33,6 +38,32 @@ use crate::models::{ForeignKeyType, ForeignKeyBehaviour};
      .await
  }
 
+/// Fetches the constraints of all kinds
+pub async fn get_all_foreign_key_constraints(
+    pool: &PgPool) -> Result<Vec<ForeignKeyConstraint>> {
+    sqlx::query_as!(
+        ForeignKeyConstraint,
+        r#"
+        SELECT 
+            id, 
+            api_version, 
+            kind, 
+            key_path, 
+            parent_key_path, 
+            type as "type: ForeignKeyType", 
+            behaviour as "behaviour: ForeignKeyBehaviour", 
+            allowed_kinds as "allowed_kinds: _",
+             acyclic,
+             edge_json_schema,
+             namespace_policy as "namespace_policy: _"
+        FROM foreign_key_constraints 
+        ORDER BY api_version, kind, key_path
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
 /// Inserts a single record
 pub async fn insert_foreign_key_constraints(
     pool: &PgPool, 
@@ -40,8 +71,8 @@ pub async fn insert_foreign_key_constraints(
 ) -> Result<()> {
     sqlx::query!(
         r#"
-        INSERT INTO foreign_key_constraints (id, api_version, kind, key_path, parent_key_path, type, behaviour, foreign_key_kind)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        INSERT INTO foreign_key_constraints (id, api_version, kind, key_path, parent_key_path, type, behaviour, allowed_kinds, acyclic, edge_json_schema, namespace_policy)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
         "#,
         row.id, 
         row.api_version, 
@@ -50,7 +81,10 @@ pub async fn insert_foreign_key_constraints(
         row.parent_key_path, 
         &row.r#type as &ForeignKeyType, 
         &row.behaviour as &ForeignKeyBehaviour, 
-        row.foreign_key_kind
+        &row.allowed_kinds as _,
+        row.acyclic,
+        row.edge_json_schema,
+        &row.namespace_policy as _
     )
     .execute(pool)
     .await?;
@@ -65,8 +99,8 @@ pub async fn insert_multiple_foreign_key_constraints(
     for row in rows {
         sqlx::query!(
             r#"
-            INSERT INTO foreign_key_constraints (id, api_version, kind, key_path, parent_key_path, type, behaviour, foreign_key_kind)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO foreign_key_constraints (id, api_version, kind, key_path, parent_key_path, type, behaviour, allowed_kinds, acyclic, edge_json_schema, namespace_policy)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
             row.id, 
             row.api_version, 
@@ -75,7 +109,10 @@ pub async fn insert_multiple_foreign_key_constraints(
             row.parent_key_path, 
             &row.r#type as &ForeignKeyType, 
             &row.behaviour as &ForeignKeyBehaviour, 
-            row.foreign_key_kind
+            &row.allowed_kinds as _,
+        row.acyclic,
+        row.edge_json_schema,
+        &row.namespace_policy as _
         )
         .execute(&mut *pool)
         .await?;
@@ -85,13 +122,13 @@ pub async fn insert_multiple_foreign_key_constraints(
 
 /// Updates an existing record based on ID
 pub async fn update_foreign_key_constraints(
-    pool: &PgPool, 
+    pool: &mut PgConnection, 
     row: &ForeignKeyConstraint
 ) -> Result<bool> {
     let result = sqlx::query!(
         r#"
         UPDATE foreign_key_constraints 
-        SET api_version = $2, kind = $3, key_path = $4, parent_key_path = $5, type = $6, behaviour = $7, foreign_key_kind = $8
+        SET api_version = $2, kind = $3, key_path = $4, parent_key_path = $5, type = $6, behaviour = $7, allowed_kinds = $8, acyclic = $9, edge_json_schema = $10, namespace_policy = $11
         WHERE id = $1
         "#,
         row.id, 
@@ -101,7 +138,10 @@ pub async fn update_foreign_key_constraints(
         row.parent_key_path, 
         &row.r#type as &ForeignKeyType, 
         &row.behaviour as &ForeignKeyBehaviour, 
-        row.foreign_key_kind
+        &row.allowed_kinds as _,
+        row.acyclic,
+        row.edge_json_schema,
+        &row.namespace_policy as _
     )
     .execute(pool)
     .await?;
@@ -109,11 +149,14 @@ pub async fn update_foreign_key_constraints(
     Ok(result.rows_affected() > 0)
 }
 
-/// Deletes a record by ID
+/// Deletes a record by ID together with its relations
 pub async fn delete_foreign_key_constraints(
-    pool: &PgPool, 
+    pool: &mut PgConnection, 
     id: Uuid
 ) -> Result<bool> {
+    sqlx::query!("DELETE FROM relations WHERE foreign_key_id = $1", id)
+        .execute(&mut *pool)
+        .await?;
     let result = sqlx::query!(
         "DELETE FROM foreign_key_constraints WHERE id = $1",
         id
@@ -124,11 +167,200 @@ pub async fn delete_foreign_key_constraints(
     Ok(result.rows_affected() > 0)
 }
 
+// unique constraints
+pub async fn get_unique_constraints(pool: &mut PgConnection, api_version: &str, kind: &str) -> Result<Vec<UniqueConstraintRow>> {
+    sqlx::query_as!(
+        UniqueConstraintRow,
+        r#"
+        SELECT id, api_version, kind, name, paths, scope as "scope: UniqueScope", foreign_key_path
+        FROM unique_constraints
+        WHERE api_version = $1 and kind = $2
+        "#,
+        api_version, kind
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn insert_unique_constraint(pool: &mut PgConnection, row: &UniqueConstraintRow) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO unique_constraints (id, api_version, kind, name, paths, scope, foreign_key_path)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        row.id, row.api_version, row.kind, row.name, &row.paths, row.scope as UniqueScope, row.foreign_key_path
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_unique_constraint(pool: &mut PgConnection, id: Uuid) -> Result<()> {
+    sqlx::query!("DELETE FROM unique_constraints WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// the string ids of every group of objects violating a unique constraint,
+/// `statement` is built from the constraint by the caller
+pub async fn get_unique_duplicates(pool: &mut PgConnection, statement: &str) -> Result<Vec<Vec<String>>> {
+    sqlx::query_scalar(statement).fetch_all(pool).await
+}
+
+/// rebuilds the values of the unique constraints scoped to a foreign key for
+/// the objects, runs after their relations are written
+pub async fn sync_unique_foreign_key_values(pool: &mut PgConnection, object_ids: &[Uuid]) -> Result<()> {
+    sqlx::query!("DELETE FROM unique_foreign_key_values WHERE object_id = ANY($1)", object_ids)
+        .execute(&mut *pool)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO unique_foreign_key_values (constraint_id, foreign_object_id, object_id, key)
+        SELECT c.id, r.foreign_object_id, o.id, k.key
+        FROM objects o
+        JOIN unique_constraints c ON c.api_version = o.api_version AND c.kind = o.kind AND c.scope = 'ForeignKey'
+        JOIN foreign_key_constraints f ON f.api_version = c.api_version AND f.kind = c.kind AND f.key_path = c.foreign_key_path
+        JOIN relations r ON r.object_id = o.id AND r.foreign_key_id = f.id
+        CROSS JOIN LATERAL (
+            SELECT ARRAY(
+                SELECT o.spec #>> string_to_array(p, '.')
+                FROM unnest(c.paths) WITH ORDINALITY AS t(p, n) ORDER BY n
+            ) AS key
+        ) k
+        WHERE o.id = ANY($1) AND array_position(k.key, NULL) IS NULL
+        "#,
+        object_ids
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// fills the values of a unique constraint scoped to a foreign key for the
+/// stored objects of its kind, duplicates are left out
+pub async fn fill_unique_foreign_key_values(pool: &mut PgConnection, constraint_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO unique_foreign_key_values (constraint_id, foreign_object_id, object_id, key)
+        SELECT c.id, r.foreign_object_id, o.id, k.key
+        FROM unique_constraints c
+        JOIN objects o ON o.api_version = c.api_version AND o.kind = c.kind
+        JOIN foreign_key_constraints f ON f.api_version = c.api_version AND f.kind = c.kind AND f.key_path = c.foreign_key_path
+        JOIN relations r ON r.object_id = o.id AND r.foreign_key_id = f.id
+        CROSS JOIN LATERAL (
+            SELECT ARRAY(
+                SELECT o.spec #>> string_to_array(p, '.')
+                FROM unnest(c.paths) WITH ORDINALITY AS t(p, n) ORDER BY n
+            ) AS key
+        ) k
+        WHERE c.id = $1 AND array_position(k.key, NULL) IS NULL
+        ON CONFLICT DO NOTHING
+        "#,
+        constraint_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_spec_indexes(pool: &mut PgConnection, api_version: &str, kind: &str) -> Result<Vec<SpecIndexRow>> {
+    sqlx::query_as!(
+        SpecIndexRow,
+        r#"
+        SELECT id, api_version, kind, path, type as "type: SpecIndexType"
+        FROM spec_indexes
+        WHERE api_version = $1 and kind = $2
+        "#,
+        api_version, kind
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn insert_spec_index(pool: &mut PgConnection, row: &SpecIndexRow) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO spec_indexes (id, api_version, kind, path, type)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        row.id, row.api_version, row.kind, row.path, row.r#type as SpecIndexType
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_spec_index(pool: &mut PgConnection, id: Uuid) -> Result<()> {
+    sqlx::query!("DELETE FROM spec_indexes WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// for statements which can not be prepared like index definitions
+pub async fn execute_ddl(pool: &mut PgConnection, statement: &str) -> Result<()> {
+    sqlx::raw_sql(statement).execute(pool).await?;
+    Ok(())
+}
+
+/// serializes the unique checks of a kind until the transaction ends
+pub async fn lock_unique_constraints(pool: &mut PgConnection, type_id: &str) -> Result<()> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('unique/' || $1))")
+        .bind(type_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// an object outside of exclude with the same values at the paths, within the
+/// namespace and the referenced object of the foreign key if given. values is
+/// a json array with the value of every path
+#[allow(clippy::too_many_arguments)]
+pub async fn find_unique_conflict(
+    pool: &mut PgConnection,
+    api_version: &str,
+    kind: &str,
+    paths: &[String],
+    values: &serde_json::Value,
+    namespace: Option<&str>,
+    foreign_key: Option<(Uuid, &str)>,
+    exclude: &[String],
+) -> Result<Option<String>> {
+    let (fk_id, fk_target) = foreign_key.unzip();
+    sqlx::query_scalar!(
+        r#"
+        SELECT o.string_id
+        FROM objects o
+        WHERE o.api_version = $1 AND o.kind = $2
+            AND NOT o.string_id = ANY($3)
+            AND ($4::text IS NULL OR o.namespace = $4)
+            AND ($5::uuid IS NULL OR EXISTS (
+                SELECT 1 FROM relations r
+                JOIN objects f ON f.id = r.foreign_object_id
+                WHERE r.object_id = o.id AND r.foreign_key_id = $5 AND f.string_id = $6
+            ))
+            -- the text of the values like the unique indexes
+            AND ARRAY(
+                SELECT o.spec #>> string_to_array(p, '.')
+                FROM unnest($7::text[]) WITH ORDINALITY AS t(p, n) ORDER BY n
+            ) = ARRAY(
+                SELECT v #>> '{}'
+                FROM jsonb_array_elements($8) WITH ORDINALITY AS e(v, n) ORDER BY n
+            )
+        LIMIT 1
+        "#,
+        api_version, kind, exclude, namespace, fk_id, fk_target, paths, values
+    )
+    .fetch_optional(pool)
+    .await
+}
+
 // object schema
 pub async fn insert_object_schema(pool: &mut PgConnection, item: &ObjectSchema) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "INSERT INTO object_schemas (id, api_version, kind, aliases, json_schema) VALUES ($1, $2, $3, $4, $5)",
-        item.id, item.api_version, item.kind, &item.aliases, item.json_schema
+        "INSERT INTO object_schemas (id, api_version, kind, aliases, json_schema, status_json_schema, ttl_seconds, immutable, validation_rules) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        item.id, item.api_version, item.kind, &item.aliases, item.json_schema, item.status_json_schema, item.ttl_seconds, item.immutable, item.validation_rules as _
     )
     .execute(pool)
     .await?;
@@ -150,7 +382,7 @@ pub async fn insert_multiple_object_schemas(pool: &sqlx::PgPool, items: &[Object
 }
 
 pub async fn get_object_schema(pool: &mut PgConnection, api_version: &str, kind: &str ) -> Result<Option<ObjectSchema>, sqlx::Error> {
-    sqlx::query_as!(ObjectSchema, "SELECT * FROM object_schemas WHERE kind = $1 and api_version = $2" , kind, api_version)
+    sqlx::query_as!(ObjectSchema, r#"SELECT id, api_version, kind, aliases, json_schema, status_json_schema, ttl_seconds, immutable, validation_rules as "validation_rules: _" FROM object_schemas WHERE kind = $1 and api_version = $2"# , kind, api_version)
         .fetch_optional(pool)
         .await
 }
@@ -166,7 +398,11 @@ pub async fn get_all_object_schemas(
             api_version, 
             kind, 
             aliases,
-            json_schema 
+            json_schema,
+            status_json_schema,
+            ttl_seconds,
+            immutable,
+            validation_rules as "validation_rules: _"
         FROM object_schemas
         "#
     )
@@ -174,10 +410,10 @@ pub async fn get_all_object_schemas(
     .await
 }
 
-pub async fn update_object_schema(pool: &sqlx::PgPool, item: &ObjectSchema) -> Result<(), sqlx::Error> {
+pub async fn update_object_schema(pool: &mut PgConnection, item: &ObjectSchema) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "UPDATE object_schemas SET api_version = $2, kind = $3, json_schema = $4 WHERE id = $1",
-        item.id, item.api_version, item.kind, item.json_schema
+        "UPDATE object_schemas SET aliases = $2, json_schema = $3, status_json_schema = $4, ttl_seconds = $5, immutable = $6, validation_rules = $7 WHERE id = $1",
+        item.id, &item.aliases, item.json_schema, item.status_json_schema, item.ttl_seconds, item.immutable, item.validation_rules as _
     )
     .execute(pool)
     .await?;
@@ -230,12 +466,13 @@ pub async fn insert_multiple_objects(pool: &mut PgConnection, items: &[Object])
     Ok(())
 }
 
-pub async fn insert_or_update_multiple_objects(pool: &mut PgConnection, items: &[Object]) -> Result<(), sqlx::Error> {
+/// returns the resource versions of the written objects
+pub async fn insert_or_update_multiple_objects(pool: &mut PgConnection, items: &[Object]) -> Result<Vec<(Uuid, i64)>, sqlx::Error> {
     if items.is_empty() {
-        return Ok(())
+        return Ok(vec![])
     }
     let mut query_builder: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
-        "INSERT INTO objects (id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations, labels, spec) "
+        "INSERT INTO objects (id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations, labels, spec, finalizers, owners, expires_at, managed_fields) "
     );
 
     query_builder.push_values(items, |mut b, item| {
@@ -249,7 +486,11 @@ pub async fn insert_or_update_multiple_objects(pool: &mut PgConnection, items: &
             .push_bind(&item.namespace)
             .push_bind(serde_json::to_value(&item.annotations).unwrap())
             .push_bind(serde_json::to_value(&item.labels).unwrap())
-            .push_bind(&item.spec.0);
+            .push_bind(&item.spec.0)
+            .push_bind(&item.finalizers)
+            .push_bind(&item.owners)
+            .push_bind(item.expires_at)
+            .push_bind(&item.managed_fields);
     });
 
     query_builder.push(
@@ -260,12 +501,14 @@ pub async fn insert_or_update_multiple_objects(pool: &mut PgConnection, items: &
     query_builder.push("updated_at = EXCLUDED.updated_at, ");
     query_builder.push("annotations = EXCLUDED.annotations, ");
     query_builder.push("labels = EXCLUDED.labels, ");
-    query_builder.push("spec = EXCLUDED.spec");
-
-    let query = query_builder.build();
-    query.execute(pool).await?;
-
-    Ok(())
+    query_builder.push("spec = EXCLUDED.spec, ");
+    query_builder.push("finalizers = EXCLUDED.finalizers, ");
+    query_builder.push("owners = EXCLUDED.owners, ");
+    query_builder.push("expires_at = EXCLUDED.expires_at, ");
+    query_builder.push("managed_fields = EXCLUDED.managed_fields");
+    query_builder.push(" RETURNING id, resource_version");
+
+    query_builder.build_query_as::<(Uuid, i64)>().fetch_all(pool).await
 }
 
 pub async fn update_multiple_objects(pool: &mut PgConnection, items: &[Object]) -> Result<(), sqlx::Error> {
@@ -310,18 +553,78 @@ pub async fn update_multiple_objects(pool: &mut PgConnection, items: &[Object])
     Ok(())
 }
 
+/// the next page of all objects ordered by id
+pub async fn get_objects_after(pool: &mut PgConnection, after: Uuid, limit: i64) -> Result<Vec<Object>, sqlx::Error> {
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE id > $1 ORDER BY id LIMIT $2", after, limit)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_object_names(pool: &mut PgConnection, string_ids: &[String], ids: &[Uuid]) -> Result<Vec<ObjectName>, sqlx::Error> {
+    sqlx::query_as!(ObjectName, "SELECT id, string_id FROM objects WHERE string_id = ANY($1) OR id = ANY($2)", string_ids, ids)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_string_id_mismatches(pool: &mut PgConnection) -> Result<Vec<StringIdMismatch>, sqlx::Error> {
+    sqlx::query_as!(StringIdMismatch, r#"SELECT id, string_id, namespace || '/' || kind || '/' || name as "expected!" FROM objects WHERE string_id <> namespace || '/' || kind || '/' || name"#)
+        .fetch_all(pool)
+        .await
+}
+
+/// false: another object already has the string id
+pub async fn update_string_id(pool: &mut PgConnection, id: Uuid, string_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("UPDATE objects SET string_id = $2 WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM objects WHERE string_id = $2)", id, string_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 pub async fn get_object(pool: &mut PgConnection, id: uuid::Uuid) -> Result<Option<Object>, sqlx::Error> {
-    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\" FROM objects WHERE id = $1", id)
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE id = $1", id)
         .fetch_optional(pool)
         .await
 }
 
 pub async fn get_objects(pool: &mut PgConnection, ids: &[uuid::Uuid]) -> Result<Vec<Object>, sqlx::Error> {
-    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\" FROM objects WHERE id = ANY($1)", ids)
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE id = ANY($1)", ids)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_object_by_string_id(pool: &mut PgConnection, string_id: &str) -> Result<Option<Object>, sqlx::Error> {
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE string_id = $1", string_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// like get_object_by_string_id, but locks the row until the transaction ends
+pub async fn lock_object_by_string_id(pool: &mut PgConnection, string_id: &str) -> Result<Option<Object>, sqlx::Error> {
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE string_id = $1 FOR UPDATE", string_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn lock_objects_by_string_ids(pool: &mut PgConnection, string_ids: &[String]) -> Result<Vec<Object>, sqlx::Error> {
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE string_id = ANY($1) ORDER BY id FOR UPDATE", string_ids)
+        .fetch_all(pool)
+        .await
+}
+
+/// objects carrying all of the labels which are not part of string_ids
+pub async fn get_objects_by_labels_excluding(pool: &mut PgConnection, labels: &serde_json::Value, string_ids: &[String]) -> Result<Vec<Object>, sqlx::Error> {
+    sqlx::query_as!(Object, "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations as \"annotations: _\", labels as \"labels: _\", spec as \"spec: _\", status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp, managed_fields as \"managed_fields: _\", resource_version FROM objects WHERE labels @> $1 AND deletion_timestamp IS NULL AND NOT string_id = ANY($2)", labels, string_ids)
         .fetch_all(pool)
         .await
 }
 
+/// returns the new resource version
+pub async fn update_object_status(pool: &mut PgConnection, id: Uuid, status: &serde_json::Value) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!("UPDATE objects SET status = $2 WHERE id = $1 RETURNING resource_version", id, status)
+        .fetch_one(pool)
+        .await
+}
+
 pub async fn object_exists(pool: &mut PgConnection, string_id: &str) -> Result<bool, sqlx::Error> {
     sqlx::query("SELECT 1 FROM objects WHERE string_id = $1")
         .bind(string_id)
@@ -330,9 +633,9 @@ pub async fn object_exists(pool: &mut PgConnection, string_id: &str) -> Result<b
         .map(|x| x.is_some())
 }
 
-pub async fn get_objects_by_filter(pool: &mut PgConnection, filter: &GetObjectsFilter) -> Result<Vec<Object>, sqlx::Error> {
+pub async fn get_objects_by_filter(pool: &mut PgConnection, filter: &GetObjectsFilter, query: Option<&query::Query>) -> Result<Vec<Object>, sqlx::Error> {
     let mut query_builder: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
-        "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations, labels, spec FROM objects where true "
+        "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations, labels, spec, status, finalizers, owners, expires_at, deletion_timestamp, managed_fields, resource_version FROM objects where true "
     );
 
     if let Some(x) = &filter.namespace {
@@ -346,9 +649,10 @@ pub async fn get_objects_by_filter(pool: &mut PgConnection, filter: &GetObjectsF
         query_builder.push(") ");
     }
 
+    // kind and paths are inlined so the planner can match the partial spec indexes,
+    // the paths are checked by the backend before
     if let Some(x) = &filter.kind {
-        query_builder.push(" and kind = ");
-        query_builder.push_bind(x);
+        query_builder.push(format!(" and kind = {}", quote_literal(x)));
     }
 
     if let Some(x) = &filter.name {
@@ -356,6 +660,31 @@ pub async fn get_objects_by_filter(pool: &mut PgConnection, filter: &GetObjectsF
         query_builder.push_bind(x);
     }
 
+    for x in filter.field_selectors.iter().flatten() {
+        let path = path_literal(&x.path);
+        match x.operator {
+            FieldSelectorOperator::Equals => {
+                query_builder.push(format!(" and (spec #>> {path}) = "));
+                query_builder.push_bind(x.value.clone());
+            }
+            FieldSelectorOperator::NotEquals => {
+                query_builder.push(format!(" and (spec #>> {path}) is distinct from "));
+                query_builder.push_bind(x.value.clone());
+            }
+            FieldSelectorOperator::Contains => {
+                // the value is taken as json and falls back to a string
+                let value = serde_json::from_str(&x.value).unwrap_or(serde_json::Value::String(x.value.clone()));
+                query_builder.push(format!(" and (spec #> {path}) @> "));
+                query_builder.push_bind(serde_json::Value::Array(vec![value]));
+            }
+        }
+    }
+
+    if let Some(x) = query {
+        query_builder.push(" and ");
+        query_impl::push_query(&mut query_builder, x, "objects");
+    }
+
     query_builder.push(" order by kind, name");
 
     if let Some(x) = &filter.page_size {
@@ -374,14 +703,14 @@ pub async fn get_objects_by_filter(pool: &mut PgConnection, filter: &GetObjectsF
 }
 
 pub async fn get_object_infos(pool: &mut PgConnection, string_ids: &[String]) -> Result<Vec<ObjectInfo>, sqlx::Error> {
-    sqlx::query_as!(ObjectInfo, "SELECT id, string_id, created_at FROM objects WHERE string_id = ANY($1)", string_ids)
+    sqlx::query_as!(ObjectInfo, "SELECT id, string_id, api_version, created_at, status as \"status: _\", finalizers, owners as \"owners: _\", expires_at, deletion_timestamp FROM objects WHERE string_id = ANY($1)", string_ids)
         .fetch_all(pool)
         .await
 }
 
 pub async fn get_api_object_infos_with_filter(pool: &mut PgConnection, filter: &GetObjectInfosFilter) -> Result<Vec<ApiObjectInfo>, sqlx::Error> {
     let mut query_builder: sqlx::QueryBuilder<sqlx::Postgres> = sqlx::QueryBuilder::new(
-        "SELECT namespace, id, api_version, name, kind where true "
+        "SELECT namespace, id, api_version, name, kind FROM objects where true "
     );
 
     if let Some(x) = &filter.namespace {
@@ -400,9 +729,9 @@ pub async fn get_api_object_infos_with_filter(pool: &mut PgConnection, filter: &
     }
 
     if let Some(x) = &filter.name_search_string {
-        query_builder.push(" and name ilike '%");
+        query_builder.push(" and name ilike '%' || ");
         query_builder.push_bind(x);
-        query_builder.push("%' ");
+        query_builder.push(" || '%' ");
     }
 
     query_builder.push(" order by kind, name ");
@@ -422,6 +751,34 @@ pub async fn get_api_object_infos_with_filter(pool: &mut PgConnection, filter: &
     query_builder.build_query_as::<ApiObjectInfo>().fetch_all(pool).await
 }
 
+/// the headline is made of the name and every string of labels, annotations and spec
+/// the built-in kinds are only searched when the kind filter names them
+pub async fn search_objects(pool: &mut PgConnection, filter: &SearchFilter, limit: i64) -> Result<Vec<SearchHitRow>, sqlx::Error> {
+    let builtin_api_versions = [LEASE_API_VERSION, EVENT_API_VERSION].map(|x| x.to_owned());
+    let builtin_kinds = [LEASE_KIND, EVENT_KIND].map(|x| x.to_owned());
+    sqlx::query_as!(
+        SearchHitRow,
+        r#"
+        SELECT namespace, id, api_version, kind, name,
+            ts_rank(search_vector, q) as "rank!",
+            ts_headline('simple', concat_ws(' ', name, (
+                SELECT string_agg(x #>> '{}', ' ')
+                FROM jsonb_path_query(jsonb_build_array(labels, annotations, spec), 'strict $.** ? (@.type() == "string")') x
+            )), q, 'MaxFragments=3, MinWords=3, MaxWords=12') as "headline!"
+        FROM objects, websearch_to_tsquery('simple', $1) q
+        WHERE search_vector @@ q
+            AND ($2::text IS NULL OR namespace = $2)
+            AND ($3::text IS NULL OR kind = $3)
+            AND ($3::text IS NOT NULL OR (api_version, kind) NOT IN (SELECT * FROM unnest($5::text[], $6::text[])))
+        ORDER BY 6 DESC, kind, name
+        LIMIT $4
+        "#,
+        filter.query, filter.namespace, filter.kind, limit, &builtin_api_versions, &builtin_kinds
+    )
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn update_object(pool: &sqlx::PgPool, item: &Object) -> Result<(), sqlx::Error> {
     sqlx::query!(
         "UPDATE objects SET api_version = $2, name = $3, kind = $4, updated_at = $5, namespace = $6, annotations = $7, labels = $8, spec = $9 WHERE id = $1",
@@ -447,6 +804,403 @@ pub async fn delete_object(pool: &mut PgConnection, namespace: Option<&str>, nam
     Ok(())
 }
 
+pub async fn get_objects_by_name(pool: &mut PgConnection, namespace: Option<&str>, name: &str, kind: &str) -> Result<Vec<Object>, sqlx::Error> {
+    let mut qb = QueryBuilder::<sqlx::Postgres>::new(
+        "SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace, annotations, labels, spec, status, finalizers, owners, expires_at, deletion_timestamp, managed_fields, resource_version FROM objects WHERE name = "
+    );
+    qb.push_bind(name).push(" and kind = ").push_bind(kind);
+    if let Some(ns) = namespace {
+         qb.push(" and namespace = ");
+         qb.push_bind(ns);
+    }
+    qb.build_query_as::<Object>().fetch_all(pool).await
+}
+
+pub async fn delete_objects(pool: &mut PgConnection, ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM objects WHERE id = ANY($1)", ids)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// only the first deletion request sets the timestamp
+pub async fn mark_objects_for_deletion(pool: &mut PgConnection, ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE objects SET deletion_timestamp = now() WHERE id = ANY($1) AND deletion_timestamp IS NULL",
+        ids
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn add_finalizer(pool: &mut PgConnection, ids: &[Uuid], finalizer: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE objects SET finalizers = array_append(finalizers, $2) WHERE id = ANY($1) AND NOT $2 = ANY(finalizers)",
+        ids,
+        finalizer
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn remove_finalizer(pool: &mut PgConnection, id: Uuid, finalizer: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE objects SET finalizers = array_remove(finalizers, $2) WHERE id = $1",
+        id,
+        finalizer
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// objects with owners where no owner is alive anymore, an owner under
+/// foreground deletion does not count as alive
+pub async fn get_orphaned_objects(pool: &mut PgConnection) -> Result<Vec<Object>, sqlx::Error> {
+    sqlx::query_as!(
+        Object,
+        r#"
+        SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        FROM objects o
+        WHERE o.owners <> '[]'::jsonb
+            AND o.deletion_timestamp IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM jsonb_array_elements(o.owners) w
+                JOIN objects p ON p.id = (w->>'id')::uuid
+                WHERE p.deletion_timestamp IS NULL OR NOT $1 = ANY(p.finalizers)
+            )
+        "#,
+        FOREGROUND_DELETION_FINALIZER
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_expired_objects(pool: &mut PgConnection) -> Result<Vec<Object>, sqlx::Error> {
+    sqlx::query_as!(
+        Object,
+        r#"
+        SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        FROM objects
+        WHERE expires_at <= now() AND deletion_timestamp IS NULL
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// owners under foreground deletion which have no dependents left
+pub async fn get_finished_foreground_deletions(pool: &mut PgConnection) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT p.id FROM objects p
+        WHERE p.deletion_timestamp IS NOT NULL
+            AND $1 = ANY(p.finalizers)
+            AND NOT EXISTS (
+                SELECT 1 FROM objects o WHERE o.owners @> jsonb_build_array(jsonb_build_object('id', p.id))
+            )
+        "#,
+        FOREGROUND_DELETION_FINALIZER
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// takes the lease if it is free, expired or already held by the holder,
+/// returns None if somebody else holds it or an object of another kind has
+/// the string id
+pub async fn acquire_lease(
+    pool: &mut PgConnection,
+    id: Uuid,
+    namespace: &str,
+    name: &str,
+    holder_identity: &str,
+    lease_duration_seconds: i64,
+) -> Result<Option<Object>, sqlx::Error> {
+    let string_id = format!("{namespace}/{LEASE_KIND}/{name}");
+    sqlx::query_as!(
+        Object,
+        r#"
+        INSERT INTO objects (id, string_id, api_version, name, kind, created_at, updated_at, namespace, spec)
+        VALUES ($1, $2, $3, $4, $5, now(), now(), $6, jsonb_build_object(
+            'holder_identity', $7::text,
+            'lease_duration_seconds', $8::bigint,
+            'acquire_time', now(),
+            'renew_time', now()
+        ))
+        ON CONFLICT (string_id) DO UPDATE SET
+            updated_at = now(),
+            spec = jsonb_build_object(
+                'holder_identity', $7::text,
+                'lease_duration_seconds', $8::bigint,
+                'acquire_time', CASE WHEN objects.spec->>'holder_identity' = $7
+                    THEN objects.spec->'acquire_time' ELSE to_jsonb(now()) END,
+                'renew_time', now()
+            )
+        WHERE objects.api_version = $3 AND objects.kind = $5 AND (
+            objects.spec->>'holder_identity' IS NULL
+            OR objects.spec->>'holder_identity' = $7
+            OR (objects.spec->>'renew_time')::timestamptz
+                + make_interval(secs => (objects.spec->>'lease_duration_seconds')::float8) < now())
+        RETURNING id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        "#,
+        id,
+        string_id,
+        LEASE_API_VERSION,
+        name,
+        LEASE_KIND,
+        namespace,
+        holder_identity,
+        lease_duration_seconds
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// returns None if the lease is not held by the holder
+/// None: keeps the stored duration
+pub async fn renew_lease(pool: &mut PgConnection, string_id: &str, holder_identity: &str, lease_duration_seconds: Option<i64>) -> Result<Option<Object>, sqlx::Error> {
+    sqlx::query_as!(
+        Object,
+        r#"
+        UPDATE objects SET updated_at = now(), spec = spec || jsonb_build_object(
+            'renew_time', now(),
+            'lease_duration_seconds', coalesce($5::bigint, (spec->>'lease_duration_seconds')::bigint)
+        )
+        WHERE string_id = $1 AND spec->>'holder_identity' = $2 AND api_version = $3 AND kind = $4
+        RETURNING id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        "#,
+        string_id,
+        holder_identity,
+        LEASE_API_VERSION,
+        LEASE_KIND,
+        lease_duration_seconds
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// returns None if the lease is not held by the holder
+pub async fn release_lease(pool: &mut PgConnection, string_id: &str, holder_identity: &str) -> Result<Option<Object>, sqlx::Error> {
+    sqlx::query_as!(
+        Object,
+        r#"
+        UPDATE objects SET updated_at = now(), spec = spec || jsonb_build_object(
+            'holder_identity', null,
+            'acquire_time', null,
+            'renew_time', null
+        )
+        WHERE string_id = $1 AND spec->>'holder_identity' = $2 AND api_version = $3 AND kind = $4
+        RETURNING id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        "#,
+        string_id,
+        holder_identity,
+        LEASE_API_VERSION,
+        LEASE_KIND
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// inserts the event or bumps count and last_seen and takes over the message of the existing
+/// one with the same name,
+/// None: an object of another kind has the string id
+pub async fn upsert_event(
+    pool: &mut PgConnection,
+    id: Uuid,
+    namespace: &str,
+    name: &str,
+    spec: &serde_json::Value,
+    ttl_seconds: i64,
+) -> Result<Option<Object>, sqlx::Error> {
+    let string_id = format!("{namespace}/{EVENT_KIND}/{name}");
+    sqlx::query_as!(
+        Object,
+        r#"
+        INSERT INTO objects (id, string_id, api_version, name, kind, created_at, updated_at, namespace, spec, expires_at)
+        VALUES ($1, $2, $3, $4, $5, now(), now(), $6,
+            $7::jsonb || jsonb_build_object('count', 1, 'first_seen', now(), 'last_seen', now()),
+            now() + make_interval(secs => $8::float8))
+        ON CONFLICT (string_id) DO UPDATE SET
+            updated_at = now(),
+            expires_at = EXCLUDED.expires_at,
+            spec = objects.spec || jsonb_build_object(
+                'message', $7::jsonb->'message',
+                'count', (objects.spec->>'count')::bigint + 1,
+                'last_seen', now()
+            )
+        WHERE objects.api_version = $3 AND objects.kind = $5
+        RETURNING id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        "#,
+        id,
+        string_id,
+        EVENT_API_VERSION,
+        name,
+        EVENT_KIND,
+        namespace,
+        spec,
+        ttl_seconds as f64
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_events_of_object(
+    pool: &mut PgConnection,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+    limit: i64,
+) -> Result<Vec<Object>, sqlx::Error> {
+    sqlx::query_as!(
+        Object,
+        r#"
+        SELECT id, string_id, api_version, name, kind, created_at, updated_at, namespace,
+            annotations as "annotations: _", labels as "labels: _", spec as "spec: _", status as "status: _",
+            finalizers, owners as "owners: _", expires_at, deletion_timestamp, managed_fields as "managed_fields: _", resource_version
+        FROM objects
+        WHERE kind = $1 AND namespace = $2
+            AND spec->'involved_object' @> jsonb_build_object('kind', $3::text, 'name', $4::text)
+        ORDER BY (spec->>'last_seen')::timestamptz DESC
+        LIMIT $5
+        "#,
+        EVENT_KIND,
+        namespace,
+        kind,
+        name,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// objects outside of `ids` which still need one of them through a required foreign key
+pub async fn get_blocking_references(pool: &mut PgConnection, ids: &[Uuid]) -> Result<Vec<BlockingReference>, sqlx::Error> {
+    sqlx::query_as!(
+        BlockingReference,
+        r#"
+        SELECT r.foreign_object_id as blocked_id, o.string_id as referenced_by
+        FROM relations r
+        JOIN objects o ON o.id = r.object_id
+        JOIN foreign_key_constraints c ON c.id = r.foreign_key_id
+        WHERE r.foreign_object_id = ANY($1)
+            AND NOT o.id = ANY($1)
+            AND o.deletion_timestamp IS NULL
+            AND c.type IN ('One', 'OneOrMany')
+        "#,
+        ids
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// the relations reachable from the object by following the references
+/// outwards, e.g. its parents and their parents
+pub async fn get_ancestor_edges(
+    pool: &mut PgConnection,
+    object_id: Uuid,
+    key_path: Option<&str>,
+    max_depth: i32,
+) -> Result<Vec<RelationEdgeRow>, sqlx::Error> {
+    sqlx::query_as!(
+        RelationEdgeRow,
+        r#"
+        WITH RECURSIVE walk (object_id, foreign_object_id, foreign_key_id, depth) AS (
+            SELECT r.object_id, r.foreign_object_id, r.foreign_key_id, 1
+            FROM relations r
+            JOIN foreign_key_constraints c ON c.id = r.foreign_key_id
+            WHERE r.object_id = $1 AND ($2::text IS NULL OR c.key_path = $2)
+            UNION
+            SELECT r.object_id, r.foreign_object_id, r.foreign_key_id, w.depth + 1
+            FROM walk w
+            JOIN relations r ON r.object_id = w.foreign_object_id
+            JOIN foreign_key_constraints c ON c.id = r.foreign_key_id
+            WHERE w.depth < $3 AND ($2::text IS NULL OR c.key_path = $2)
+        )
+        SELECT w.object_id as "object_id!", w.foreign_object_id as "foreign_object_id!", c.key_path, r.properties as "properties: _", MIN(w.depth) as "depth!"
+        FROM walk w
+        JOIN foreign_key_constraints c ON c.id = w.foreign_key_id
+        JOIN relations r ON r.object_id = w.object_id AND r.foreign_object_id = w.foreign_object_id AND r.foreign_key_id = w.foreign_key_id
+        GROUP BY w.object_id, w.foreign_object_id, c.key_path, r.properties
+        ORDER BY 5
+        "#,
+        object_id,
+        key_path,
+        max_depth
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// the relations reachable from the object by following the references
+/// inwards, e.g. its children and their children
+pub async fn get_descendant_edges(
+    pool: &mut PgConnection,
+    object_id: Uuid,
+    key_path: Option<&str>,
+    max_depth: i32,
+) -> Result<Vec<RelationEdgeRow>, sqlx::Error> {
+    sqlx::query_as!(
+        RelationEdgeRow,
+        r#"
+        WITH RECURSIVE walk (object_id, foreign_object_id, foreign_key_id, depth) AS (
+            SELECT r.object_id, r.foreign_object_id, r.foreign_key_id, 1
+            FROM relations r
+            JOIN foreign_key_constraints c ON c.id = r.foreign_key_id
+            WHERE r.foreign_object_id = $1 AND ($2::text IS NULL OR c.key_path = $2)
+            UNION
+            SELECT r.object_id, r.foreign_object_id, r.foreign_key_id, w.depth + 1
+            FROM walk w
+            JOIN relations r ON r.foreign_object_id = w.object_id
+            JOIN foreign_key_constraints c ON c.id = r.foreign_key_id
+            WHERE w.depth < $3 AND ($2::text IS NULL OR c.key_path = $2)
+        )
+        SELECT w.object_id as "object_id!", w.foreign_object_id as "foreign_object_id!", c.key_path, r.properties as "properties: _", MIN(w.depth) as "depth!"
+        FROM walk w
+        JOIN foreign_key_constraints c ON c.id = w.foreign_key_id
+        JOIN relations r ON r.object_id = w.object_id AND r.foreign_object_id = w.foreign_object_id AND r.foreign_key_id = w.foreign_key_id
+        GROUP BY w.object_id, w.foreign_object_id, c.key_path, r.properties
+        ORDER BY 5
+        "#,
+        object_id,
+        key_path,
+        max_depth
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// relations of deleted objects or foreign key constraints
+pub async fn get_dangling_relations(pool: &mut PgConnection) -> Result<Vec<Relation>, sqlx::Error> {
+    sqlx::query_as!(
+        Relation,
+        r#"
+        SELECT r.object_id, r.foreign_object_id, r.foreign_key_id, r.properties as "properties: _"
+        FROM relations r
+        LEFT JOIN objects o ON o.id = r.object_id
+        LEFT JOIN objects f ON f.id = r.foreign_object_id
+        LEFT JOIN foreign_key_constraints c ON c.id = r.foreign_key_id
+        WHERE o.id IS NULL OR f.id IS NULL OR c.id IS NULL
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_relation(
     pool: &mut PgConnection,
     object_id: Uuid,
@@ -456,7 +1210,7 @@ pub async fn get_relation(
     sqlx::query_as!(
         Relation,
         r#"
-        SELECT object_id, foreign_object_id, foreign_key_id 
+        SELECT object_id, foreign_object_id, foreign_key_id, properties as "properties: _"
         FROM relations 
         WHERE object_id = $1 AND foreign_object_id = $2 AND foreign_key_id = $3
         "#,
@@ -475,7 +1229,7 @@ pub async fn get_relations_of_objects(
     sqlx::query_as!(
         Relation,
         r#"
-        SELECT object_id, foreign_object_id, foreign_key_id 
+        SELECT object_id, foreign_object_id, foreign_key_id, properties as "properties: _"
         FROM relations 
         WHERE object_id = ANY($1)
         "#,
@@ -489,12 +1243,13 @@ pub async fn get_relations_of_objects(
 pub async fn insert_relation(pool: &mut PgConnection, relation: &Relation) -> Result<(), sqlx::Error> {
     sqlx::query!(
         r#"
-        INSERT INTO relations (object_id, foreign_object_id, foreign_key_id)
-        VALUES ($1, $2, $3)
+        INSERT INTO relations (object_id, foreign_object_id, foreign_key_id, properties)
+        VALUES ($1, $2, $3, $4)
         "#,
         relation.object_id,
         relation.foreign_object_id,
-        relation.foreign_key_id
+        relation.foreign_key_id,
+        relation.properties as _
     )
     .execute(pool)
     .await?;
@@ -524,6 +1279,17 @@ pub async fn delete_relation(
     Ok(result.rows_affected())
 }
 
+/// Deletes the relations from and to the given objects
+pub async fn delete_relations_of_objects(pool: &mut PgConnection, ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM relations WHERE object_id = ANY($1) OR foreign_object_id = ANY($1)",
+        ids
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
 pub async fn insert_multiple_relation(
     pool: &mut PgConnection,
     relations: &[Relation],
@@ -533,17 +1299,19 @@ pub async fn insert_multiple_relation(
     }
 
     let mut query_builder  = QueryBuilder::new(
-        "INSERT INTO relations (object_id, foreign_object_id, foreign_key_id) "
+        "INSERT INTO relations (object_id, foreign_object_id, foreign_key_id, properties) "
     );
 
     query_builder.push_values(relations, |mut b, rel| {
         b.push_bind(rel.object_id)
          .push_bind(rel.foreign_object_id)
-         .push_bind(rel.foreign_key_id);
+         .push_bind(rel.foreign_key_id)
+         .push_bind(&rel.properties);
     });
 
+    // the properties of a reference can change without the reference
     query_builder.push(
-        " ON CONFLICT (object_id, foreign_object_id, foreign_key_id) DO NOTHING "
+        " ON CONFLICT (object_id, foreign_object_id, foreign_key_id) DO UPDATE SET properties = EXCLUDED.properties "
     );
 
     let query = query_builder.build();
@@ -553,6 +1321,41 @@ pub async fn insert_multiple_relation(
 }
 
 
+/// string ids of a cycle over the relations of the key which passes one of the
+/// objects, the first object is repeated at the end. empty: no cycle
+pub async fn find_relation_cycle(
+    pool: &mut PgConnection,
+    foreign_key_id: Uuid,
+    object_ids: &[Uuid],
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        WITH RECURSIVE walk (start_id, object_id, path, is_cycle) AS (
+            SELECT object_id, foreign_object_id, ARRAY[object_id, foreign_object_id], object_id = foreign_object_id
+            FROM relations
+            WHERE foreign_key_id = $1 AND object_id = ANY($2)
+            UNION ALL
+            SELECT w.start_id, r.foreign_object_id, w.path || r.foreign_object_id, r.foreign_object_id = w.start_id
+            FROM walk w
+            JOIN relations r ON r.object_id = w.object_id AND r.foreign_key_id = $1
+            WHERE NOT w.is_cycle
+              AND (r.foreign_object_id = w.start_id OR NOT r.foreign_object_id = ANY(w.path))
+        ),
+        cycle AS (
+            SELECT path FROM walk WHERE is_cycle LIMIT 1
+        )
+        SELECT o.string_id as "string_id!"
+        FROM cycle, UNNEST(cycle.path) WITH ORDINALITY AS p(id, ord)
+        JOIN objects o ON o.id = p.id
+        ORDER BY p.ord
+        "#,
+        foreign_key_id,
+        object_ids
+    )
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn delete_multiple_relations(
     pool: &mut PgConnection,
     object_ids: &[Uuid],
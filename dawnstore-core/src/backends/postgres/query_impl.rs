@@ -0,0 +1,193 @@
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::backends::postgres::index_impl::path_literal;
+
+use dawnstore_lib::query::{Field, Operator, Query};
+
+/// the text form of a value compared with a column, null stays null
+fn text_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(x) => Some(x.clone()),
+        x => Some(x.to_string()),
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn sql_operator(operator: Operator) -> &'static str {
+    match operator {
+        Operator::Equals => "=",
+        Operator::NotEquals => "!=",
+        Operator::Less => "<",
+        Operator::LessOrEqual => "<=",
+        Operator::Greater => ">",
+        Operator::GreaterOrEqual => ">=",
+        Operator::Contains => "@>",
+    }
+}
+
+/// pushes the text column of a field, spec and ref are handled by the caller
+fn push_text_column(qb: &mut QueryBuilder<'_, Postgres>, field: &Field, table: &str) {
+    match field {
+        Field::Namespace => qb.push(format!("{table}.namespace")),
+        Field::Kind => qb.push(format!("{table}.kind")),
+        Field::Name => qb.push(format!("{table}.name")),
+        Field::ApiVersion => qb.push(format!("{table}.api_version")),
+        Field::Label(key) => qb
+            .push(format!("({table}.labels ->> "))
+            .push_bind(key.clone())
+            .push(")"),
+        Field::Annotation(key) => qb
+            .push(format!("({table}.annotations ->> "))
+            .push_bind(key.clone())
+            .push(")"),
+        Field::Spec(_) | Field::Ref { .. } => unreachable!(),
+    };
+}
+
+/// every comparison is true or false, missing fields never make it null
+fn push_compare(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    field: &Field,
+    operator: Operator,
+    value: &Value,
+    table: &str,
+    depth: usize,
+) {
+    match field {
+        Field::Ref { key_path, field } => {
+            let (r, f, o) = (
+                format!("r{depth}"),
+                format!("f{depth}"),
+                format!("o{depth}"),
+            );
+            qb.push(format!(
+                "exists (select 1 from relations {r} \
+                 join foreign_key_constraints {f} on {f}.id = {r}.foreign_key_id \
+                 join objects {o} on {o}.id = {r}.foreign_object_id \
+                 where {r}.object_id = {table}.id and {f}.key_path = "
+            ));
+            qb.push_bind(key_path.clone());
+            qb.push(" and ");
+            push_compare(qb, field, operator, value, &o, depth + 1);
+            qb.push(")");
+        }
+        // the segments are plain words or indexes, the path is inlined like
+        // the one of the field selectors. values are compared as json, a
+        // missing field is json null
+        Field::Spec(path) => {
+            let operand = format!(
+                "coalesce({table}.spec #> {}, 'null'::jsonb)",
+                path_literal(&path.join("."))
+            );
+            match operator {
+                Operator::Equals | Operator::NotEquals => {
+                    qb.push(format!("{operand} {} ", sql_operator(operator)));
+                    qb.push_bind(value.clone());
+                }
+                // only values of the same json type are ordered
+                Operator::Less
+                | Operator::LessOrEqual
+                | Operator::Greater
+                | Operator::GreaterOrEqual => {
+                    qb.push(format!(
+                        "(jsonb_typeof({operand}) = '{}' and {operand} {} ",
+                        json_type(value),
+                        sql_operator(operator)
+                    ));
+                    qb.push_bind(value.clone());
+                    qb.push(")");
+                }
+                Operator::Contains => {
+                    qb.push(format!("({operand} @> "));
+                    qb.push_bind(Value::Array(vec![value.clone()]));
+                    qb.push(format!(
+                        " or (jsonb_typeof({operand}) = 'string' and strpos({operand} #>> '{{}}', "
+                    ));
+                    qb.push_bind(text_value(value).unwrap_or_default());
+                    qb.push(") > 0))");
+                }
+            }
+        }
+        field => match operator {
+            Operator::Equals => {
+                push_text_column(qb, field, table);
+                qb.push(" is not distinct from ");
+                qb.push_bind(text_value(value));
+            }
+            Operator::NotEquals => {
+                push_text_column(qb, field, table);
+                qb.push(" is distinct from ");
+                qb.push_bind(text_value(value));
+            }
+            Operator::Less
+            | Operator::LessOrEqual
+            | Operator::Greater
+            | Operator::GreaterOrEqual => {
+                qb.push("coalesce(");
+                push_text_column(qb, field, table);
+                qb.push(format!(" {} ", sql_operator(operator)));
+                qb.push_bind(text_value(value));
+                qb.push(", false)");
+            }
+            Operator::Contains => {
+                qb.push("coalesce(strpos(");
+                push_text_column(qb, field, table);
+                qb.push(", ");
+                qb.push_bind(text_value(value));
+                qb.push(") > 0, false)");
+            }
+        },
+    }
+}
+
+/// pushes the condition of a parsed query on the objects of `table`, the
+/// parser limits how deep this recurses
+pub fn push_query(qb: &mut QueryBuilder<'_, Postgres>, query: &Query, table: &str) {
+    match query {
+        Query::And(terms) | Query::Or(terms) => {
+            qb.push("(");
+            for (i, term) in terms.iter().enumerate() {
+                if i > 0 {
+                    qb.push(match query {
+                        Query::And(_) => " and ",
+                        _ => " or ",
+                    });
+                }
+                push_query(qb, term, table);
+            }
+            qb.push(")");
+        }
+        Query::Not(x) => {
+            qb.push("not (");
+            push_query(qb, x, table);
+            qb.push(")");
+        }
+        Query::Compare {
+            field,
+            operator,
+            value,
+        } => push_compare(qb, field, *operator, value, table, 0),
+        Query::In { field, values } => {
+            qb.push("(");
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    qb.push(" or ");
+                }
+                push_compare(qb, field, Operator::Equals, value, table, 0);
+            }
+            qb.push(")");
+        }
+    }
+}
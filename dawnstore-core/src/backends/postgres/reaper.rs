@@ -0,0 +1,56 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+
+use crate::{
+    backends::postgres::{PostgresBackend, delete_impl, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+impl PostgresBackend {
+    /// deletes all expired objects through the normal delete path, so
+    /// finalizers still block their removal
+    pub async fn reap_expired_objects(&self) -> Result<(), DawnStoreError> {
+        let mut con = self.pool.begin().await?;
+        let expired = queries::get_expired_objects(con.as_mut()).await?;
+        let blocked = delete_impl::get_blocking_references(con.as_mut(), &expired).await?;
+        let (blocked_expired, expired): (Vec<_>, Vec<_>) = expired
+            .into_iter()
+            .partition(|x| blocked.contains_key(&x.id));
+        if !expired.is_empty() {
+            tracing::info!("deleting {} expired objects", expired.len());
+        }
+        delete_impl::delete_objects(con.as_mut(), expired.as_slice()).await?;
+        con.commit().await?;
+
+        for obj in &blocked_expired {
+            let referenced_by = blocked.get(&obj.id).cloned().unwrap_or_default();
+            self.emit_event(
+                obj.reference(),
+                EventType::Warning,
+                "DeletionBlocked",
+                format!(
+                    "expired but still referenced by {}",
+                    referenced_by.join(", ")
+                ),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    pub fn spawn_expiry_reaper(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = backend.reap_expired_objects().await {
+                    tracing::error!("reaping expired objects failed: {e}");
+                }
+            }
+        })
+    }
+}
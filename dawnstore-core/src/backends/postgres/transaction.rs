@@ -0,0 +1,156 @@
+use sqlx::PgConnection;
+
+use crate::{
+    backends::postgres::{PostgresBackend, apply_impl, pruned_events, queries},
+    error::DawnStoreError,
+};
+
+use dawnstore_lib::*;
+
+async fn check_precondition(
+    con: &mut PgConnection,
+    string_id: String,
+    precondition: &Precondition,
+) -> Result<(), DawnStoreError> {
+    // locked, so a concurrent transaction can not change it after the check
+    let obj = queries::lock_object_by_string_id(con, &string_id).await?;
+    let reason = match (&obj, precondition.exists, precondition.resource_version) {
+        (Some(_), Some(false), _) => "object exists".to_string(),
+        (None, Some(true), _) | (None, _, Some(_)) => "object does not exist".to_string(),
+        (Some(x), _, Some(v)) if x.resource_version != v => {
+            format!("resource version is {} not {v}", x.resource_version)
+        }
+        _ => return Ok(()),
+    };
+    Err(DawnStoreError::PreconditionFailed {
+        object: string_id,
+        reason,
+    })
+}
+
+/// absent objects can not be locked, a concurrent transaction creating the
+/// same object makes the insert violate the unique string id instead
+fn string_id_conflict(error: DawnStoreError, string_ids: &[String]) -> DawnStoreError {
+    let DawnStoreError::DatabaseError(sqlx::Error::Database(e)) = &error else {
+        return error;
+    };
+    if !e.is_unique_violation() || e.constraint() != Some("idx_objects_string_id_lookup") {
+        return error;
+    }
+    // the detail is `Key (string_id)=(default/kind/name) already exists.`
+    let object = e
+        .try_downcast_ref::<sqlx::postgres::PgDatabaseError>()
+        .and_then(|x| x.detail())
+        .and_then(|x| x.split_once("=(")?.1.rsplit_once(')'))
+        .map(|x| x.0.to_owned())
+        .unwrap_or_else(|| string_ids.join(", "));
+    DawnStoreError::PreconditionFailed {
+        object,
+        reason: "object exists".to_string(),
+    }
+}
+
+fn string_id(namespace: Option<&str>, kind: &str, name: &str) -> String {
+    format!("{}/{}/{}", namespace.unwrap_or("default"), kind, name)
+}
+
+impl PostgresBackend {
+    /// runs the operations in order in a single database transaction, the
+    /// first failing operation rolls back all of them
+    pub async fn transaction(
+        &self,
+        transaction: Transaction,
+    ) -> Result<Vec<TransactionResult>, DawnStoreError> {
+        let mut events = Vec::new();
+        let result = self.transaction_in(transaction, &mut events).await;
+        self.emit_events(events).await;
+        result
+    }
+
+    async fn transaction_in(
+        &self,
+        transaction: Transaction,
+        events: &mut Vec<RecordEvent>,
+    ) -> Result<Vec<TransactionResult>, DawnStoreError> {
+        let mut con = self.pool.begin().await?;
+        let mut results = Vec::with_capacity(transaction.operations.len());
+        let mut pruned = Vec::new();
+        for (index, op) in transaction.operations.into_iter().enumerate() {
+            let result = async {
+                match op {
+                    TransactionOperation::Apply {
+                        object,
+                        options,
+                        precondition,
+                    } => {
+                        let input_objects = apply_impl::build_base_objects_from_raw_value(object)?;
+                        let mut sids = Vec::new();
+                        if let Some(p) = &precondition {
+                            for obj in &input_objects {
+                                let Some(kind) = &obj.kind else {
+                                    return Err(DawnStoreError::KindMissingInObject);
+                                };
+                                let sid = string_id(obj.namespace.as_deref(), kind, &obj.name);
+                                check_precondition(con.as_mut(), sid.clone(), p).await?;
+                                sids.push(sid);
+                            }
+                        }
+                        let (objects, p) = self
+                            .apply_in(con.as_mut(), input_objects, &options, &mut *events)
+                            .await
+                            .map_err(|e| match precondition {
+                                Some(Precondition {
+                                    exists: Some(false),
+                                    ..
+                                }) => string_id_conflict(e, sids.as_slice()),
+                                _ => e,
+                            })?;
+                        pruned.extend(p);
+                        Ok(TransactionResult::Apply {
+                            objects: objects.into_iter().map(ReturnAny::from).collect(),
+                        })
+                    }
+                    TransactionOperation::Patch {
+                        patch,
+                        precondition,
+                    } => {
+                        if let Some(p) = &precondition {
+                            let sid =
+                                string_id(patch.namespace.as_deref(), &patch.kind, &patch.name);
+                            check_precondition(con.as_mut(), sid, p).await?;
+                        }
+                        let object = self.patch_in(con.as_mut(), &patch, &mut *events).await?;
+                        Ok(TransactionResult::Patch {
+                            object: Box::new(object.into()),
+                        })
+                    }
+                    TransactionOperation::Delete {
+                        delete,
+                        precondition,
+                    } => {
+                        if let Some(p) = &precondition {
+                            let sid =
+                                string_id(delete.namespace.as_deref(), &delete.kind, &delete.name);
+                            check_precondition(con.as_mut(), sid, p).await?;
+                        }
+                        self.delete_in(con.as_mut(), &delete, &mut *events).await?;
+                        Ok(TransactionResult::Delete)
+                    }
+                }
+            }
+            .await;
+            match result {
+                Ok(x) => results.push(x),
+                Err(e) => {
+                    return Err(DawnStoreError::TransactionOperationFailed {
+                        index,
+                        error: Box::new(e),
+                    });
+                }
+            }
+        }
+        con.commit().await?;
+        events.extend(pruned_events(pruned.as_slice()));
+        Ok(results)
+    }
+}
@@ -0,0 +1,265 @@
+use serde_json::Value;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    backends::postgres::{
+        data_models::{ForeignKeyConstraint, UniqueConstraintRow},
+        index_impl::{is_plain_path, path_literal, quote_literal},
+        queries,
+    },
+    error::DawnStoreError,
+    models::{UniqueConstraint, UniqueScope},
+};
+
+use dawnstore_lib::*;
+
+fn validate_paths(constraint: &UniqueConstraint) -> Result<(), DawnStoreError> {
+    for path in &constraint.paths {
+        if !is_plain_path(path) {
+            return Err(DawnStoreError::InvalidUniqueConstraintPath {
+                constraint: constraint.name.clone(),
+                path: path.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// the text of the values, the same expression is used by the index, the
+/// check during apply and unique_foreign_key_values
+fn key_columns(row: &UniqueConstraintRow, table: &str) -> Vec<String> {
+    row.paths
+        .iter()
+        .map(|x| format!("({table}.spec #>> {})", path_literal(x)))
+        .collect()
+}
+
+/// constraints scoped to a foreign key have no index, the relations live
+/// in another table and are covered by unique_foreign_key_values
+fn index_definition(row: &UniqueConstraintRow) -> Option<String> {
+    let mut columns = key_columns(row, "objects");
+    match row.scope {
+        UniqueScope::Global => {}
+        UniqueScope::Namespace => columns.insert(0, "namespace".to_string()),
+        UniqueScope::ForeignKey => return None,
+    }
+    Some(format!(
+        "CREATE UNIQUE INDEX IF NOT EXISTS {} ON objects ({}) WHERE api_version = {} AND kind = {}",
+        row.index_name(),
+        columns.join(", "),
+        quote_literal(&row.api_version),
+        quote_literal(&row.kind),
+    ))
+}
+
+/// the string ids of every group of stored objects with the same values
+fn duplicates_statement(row: &UniqueConstraintRow, foreign_key_id: Option<Uuid>) -> String {
+    let columns = key_columns(row, "o");
+    let (join, scope) = match foreign_key_id {
+        None if row.scope == UniqueScope::Global => (String::new(), "true".to_string()),
+        None => (String::new(), "o.namespace".to_string()),
+        Some(id) => (
+            format!(
+                "JOIN relations r ON r.object_id = o.id AND r.foreign_key_id = {}",
+                quote_literal(&id.to_string())
+            ),
+            "r.foreign_object_id".to_string(),
+        ),
+    };
+    format!(
+        "SELECT array_agg(o.string_id ORDER BY o.string_id) FROM objects o {join} \
+         WHERE o.api_version = {} AND o.kind = {} AND {} \
+         GROUP BY {scope}, {} HAVING count(*) > 1",
+        quote_literal(&row.api_version),
+        quote_literal(&row.kind),
+        columns
+            .iter()
+            .map(|x| format!("{x} IS NOT NULL"))
+            .collect::<Vec<_>>()
+            .join(" AND "),
+        columns.join(", "),
+    )
+}
+
+/// creates the index or fills the values of a constraint, stored objects
+/// violating it are logged and the constraint is only checked during apply
+/// until they are fixed
+async fn enforce_constraint(
+    con: &mut PgConnection,
+    row: &UniqueConstraintRow,
+) -> Result<(), DawnStoreError> {
+    let foreign_key_id = match (row.scope, &row.foreign_key_path) {
+        (UniqueScope::ForeignKey, Some(path)) => {
+            let foreign_key =
+                queries::get_foreign_key_constraints(&mut *con, &row.api_version, &row.kind)
+                    .await?
+                    .into_iter()
+                    .find(|x| &x.key_path == path);
+            // validated against the declared foreign keys when it is seeded
+            let Some(foreign_key) = foreign_key else {
+                return Ok(());
+            };
+            Some(foreign_key.id)
+        }
+        _ => None,
+    };
+    let duplicates =
+        queries::get_unique_duplicates(&mut *con, &duplicates_statement(row, foreign_key_id))
+            .await?;
+    if !duplicates.is_empty() {
+        tracing::warn!(
+            "unique constraint {} of {}/{} is violated by stored objects: {}",
+            row.name,
+            row.api_version,
+            row.kind,
+            duplicates
+                .iter()
+                .map(|x| x.join(", "))
+                .collect::<Vec<_>>()
+                .join("; ")
+        );
+        return Ok(());
+    }
+    match index_definition(row) {
+        Some(ddl) => queries::execute_ddl(&mut *con, &ddl).await?,
+        None => queries::fill_unique_foreign_key_values(&mut *con, row.id).await?,
+    }
+    Ok(())
+}
+
+/// brings the registered constraints and their indexes of a kind in line
+/// with the declared ones
+pub async fn sync_unique_constraints(
+    con: &mut PgConnection,
+    api_version: &str,
+    kind: &str,
+    constraints: &[UniqueConstraint],
+) -> Result<(), DawnStoreError> {
+    let existing = queries::get_unique_constraints(&mut *con, api_version, kind).await?;
+    let matches = |row: &UniqueConstraintRow, c: &UniqueConstraint| {
+        row.name == c.name
+            && row.paths == c.paths
+            && row.scope == c.scope
+            && row.foreign_key_path == c.foreign_key_path
+    };
+    for row in &existing {
+        if !constraints.iter().any(|c| matches(row, c)) {
+            queries::execute_ddl(
+                &mut *con,
+                &format!("DROP INDEX IF EXISTS {}", row.index_name()),
+            )
+            .await?;
+            queries::delete_unique_constraint(&mut *con, row.id).await?;
+        }
+    }
+    for c in constraints {
+        // retried on every seed while stored objects violate it
+        if let Some(row) = existing.iter().find(|row| matches(row, c)) {
+            enforce_constraint(&mut *con, row).await?;
+            continue;
+        }
+        validate_paths(c)?;
+        let row = UniqueConstraintRow {
+            id: Uuid::new_v4(),
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+            name: c.name.clone(),
+            paths: c.paths.clone(),
+            scope: c.scope,
+            foreign_key_path: c.foreign_key_path.clone(),
+        };
+        queries::insert_unique_constraint(&mut *con, &row).await?;
+        enforce_constraint(&mut *con, &row).await?;
+    }
+    Ok(())
+}
+
+/// constraint name, scope and values of an object, used to find duplicates
+/// within a single apply
+pub type UniqueKey = (String, Option<String>, String);
+
+/// checks the object against the stored objects outside of the payload and
+/// returns its keys, the caller compares the keys within the payload
+#[allow(clippy::too_many_arguments)]
+pub async fn check_unique_constraints(
+    con: &mut PgConnection,
+    constraints: &[UniqueConstraintRow],
+    foreign_keys: &[ForeignKeyConstraint],
+    obj: &ObjectAny,
+    ns: &str,
+    string_id: &str,
+    fks: &[(Vec<String>, Uuid)],
+    payload: &[String],
+) -> Result<Vec<UniqueKey>, DawnStoreError> {
+    let mut keys = Vec::new();
+    let Some(first) = constraints.first() else {
+        return Ok(keys);
+    };
+    let type_id = format!("{}/{}", first.api_version, first.kind);
+    queries::lock_unique_constraints(&mut *con, &type_id).await?;
+
+    'constraints: for c in constraints {
+        let mut values = Vec::with_capacity(c.paths.len());
+        for path in &c.paths {
+            let pointer = format!("/{}", path.replace('.', "/"));
+            match obj.spec.pointer(&pointer) {
+                None | Some(Value::Null) => continue 'constraints,
+                Some(x) => values.push(x.clone()),
+            }
+        }
+        // compared by their text within the payload as well, `1` equals `"1"`
+        let text = values
+            .iter()
+            .map(|x| match x {
+                Value::String(x) => x.clone(),
+                x => x.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let values = Value::Array(values);
+
+        // (namespace, (foreign key, referenced object)) of every scope the object is in
+        let scopes = match c.scope {
+            UniqueScope::Global => vec![(None, None)],
+            UniqueScope::Namespace => vec![(Some(ns), None)],
+            UniqueScope::ForeignKey => {
+                let Some(fk) = foreign_keys
+                    .iter()
+                    .find(|x| Some(&x.key_path) == c.foreign_key_path.as_ref())
+                else {
+                    continue;
+                };
+                fks.iter()
+                    .filter(|(_, id)| *id == fk.id)
+                    .flat_map(|(targets, _)| targets.iter())
+                    .map(|target| (None, Some((fk.id, target.as_str()))))
+                    .collect()
+            }
+        };
+        for (namespace, foreign_key) in scopes {
+            let conflict = queries::find_unique_conflict(
+                &mut *con,
+                &c.api_version,
+                &c.kind,
+                c.paths.as_slice(),
+                &values,
+                namespace,
+                foreign_key,
+                payload,
+            )
+            .await?;
+            if let Some(conflicting) = conflict {
+                return Err(DawnStoreError::UniqueConstraintViolation {
+                    constraint: c.name.clone(),
+                    object: string_id.to_owned(),
+                    conflicting,
+                });
+            }
+            let scope = namespace
+                .map(|x| x.to_owned())
+                .or(foreign_key.map(|(_, target)| target.to_owned()));
+            keys.push((c.name.clone(), scope, Value::from(text.clone()).to_string()));
+        }
+    }
+    Ok(keys)
+}
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
-use crate::backends::postgres::PostgresBackend;
+use crate::{backends::postgres::PostgresBackend, error::DawnStoreError};
 use axum::{
     Json, Router,
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::{delete, post},
@@ -13,10 +13,22 @@ use dawnstore_lib::*;
 pub fn get_dawnstore_default_routes(backend: Arc<PostgresBackend>) -> Router {
     Router::new()
         .route("/apply", post(apply))
+        .route("/patch", post(patch))
+        .route("/transaction", post(transaction))
         .route("/get-objects", post(get_objects))
         .route("/get-object-infos", post(get_object_infos))
+        .route("/search", post(search))
         .route("/get-resource-definitions", post(get_resource_definitions))
+        .route("/traverse", post(traverse))
         .route("/delete-object", delete(delete_object))
+        .route("/update-status", post(update_status))
+        .route("/remove-finalizer", post(remove_finalizer))
+        .route("/acquire-lease", post(acquire_lease))
+        .route("/renew-lease", post(renew_lease))
+        .route("/release-lease", post(release_lease))
+        .route("/record-event", post(record_event))
+        .route("/get-events", post(get_events))
+        .route("/admin/fsck", post(fsck))
         .with_state(ApiState { backend })
 }
 
@@ -25,14 +37,28 @@ struct ApiState {
     backend: Arc<PostgresBackend>,
 }
 
-async fn apply(State(state): State<ApiState>, Json(obj): Json<serde_json::Value>) -> Response {
-    match state.backend.apply_raw(obj).await {
+async fn apply(
+    State(state): State<ApiState>,
+    Query(options): Query<ApplyOptions>,
+    Json(obj): Json<serde_json::Value>,
+) -> Response {
+    match state.backend.apply_raw(obj, &options).await {
         Ok(x) => Json(x).into_response(),
-        Err(y) => {
-            let mut resp = format!("{y}:{y:?}").into_response();
-            *resp.status_mut() = StatusCode::BAD_REQUEST;
-            resp
-        }
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn patch(State(state): State<ApiState>, Json(query): Json<PatchObject>) -> Response {
+    match state.backend.patch(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn transaction(State(state): State<ApiState>, Json(query): Json<Transaction>) -> Response {
+    match state.backend.transaction(query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
     }
 }
 
@@ -42,11 +68,7 @@ async fn get_objects(
 ) -> Response {
     match state.backend.get(&query).await {
         Ok(x) => Json(x).into_response(),
-        Err(y) => {
-            let mut resp = format!("{y:?}").into_response();
-            *resp.status_mut() = StatusCode::BAD_REQUEST;
-            resp
-        }
+        Err(y) => y.into_response(),
     }
 }
 
@@ -56,11 +78,14 @@ async fn get_object_infos(
 ) -> Response {
     match state.backend.get_object_infos(&query).await {
         Ok(x) => Json(x).into_response(),
-        Err(y) => {
-            let mut resp = format!("{y:?}").into_response();
-            *resp.status_mut() = StatusCode::BAD_REQUEST;
-            resp
-        }
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn search(State(state): State<ApiState>, Json(query): Json<SearchFilter>) -> Response {
+    match state.backend.search(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
     }
 }
 
@@ -70,21 +95,93 @@ async fn get_resource_definitions(
 ) -> Response {
     match state.backend.get_resource_definition(&query).await {
         Ok(x) => Json(x).into_response(),
-        Err(y) => {
-            let mut resp = format!("{y:?}").into_response();
-            *resp.status_mut() = StatusCode::BAD_REQUEST;
-            resp
-        }
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn traverse(State(state): State<ApiState>, Json(query): Json<TraverseFilter>) -> Response {
+    match state.backend.traverse(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
     }
 }
 
 async fn delete_object(State(state): State<ApiState>, Json(query): Json<DeleteObject>) -> Response {
     match state.backend.delete(&query).await {
         Ok(x) => Json(x).into_response(),
-        Err(y) => {
-            let mut resp = format!("{y:?}").into_response();
-            *resp.status_mut() = StatusCode::BAD_REQUEST;
-            resp
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn update_status(
+    State(state): State<ApiState>,
+    Json(query): Json<UpdateObjectStatus>,
+) -> Response {
+    match state.backend.update_status(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn remove_finalizer(
+    State(state): State<ApiState>,
+    Json(query): Json<RemoveFinalizer>,
+) -> Response {
+    match state.backend.remove_finalizer(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+impl IntoResponse for DawnStoreError {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        if status.is_server_error() {
+            tracing::error!("request failed: {self}");
         }
+        (status, Json(self.to_response())).into_response()
+    }
+}
+
+async fn acquire_lease(State(state): State<ApiState>, Json(query): Json<LeaseRequest>) -> Response {
+    match state.backend.acquire_lease(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn renew_lease(State(state): State<ApiState>, Json(query): Json<LeaseRequest>) -> Response {
+    match state.backend.renew_lease(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn release_lease(State(state): State<ApiState>, Json(query): Json<LeaseRequest>) -> Response {
+    match state.backend.release_lease(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn record_event(State(state): State<ApiState>, Json(query): Json<RecordEvent>) -> Response {
+    match state.backend.record_event(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn get_events(State(state): State<ApiState>, Json(query): Json<GetEventsFilter>) -> Response {
+    match state.backend.get_events(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
+    }
+}
+
+async fn fsck(State(state): State<ApiState>, Json(query): Json<FsckOptions>) -> Response {
+    match state.backend.fsck(&query).await {
+        Ok(x) => Json(x).into_response(),
+        Err(y) => y.into_response(),
     }
 }
@@ -1,7 +1,7 @@
 use jsonschema::ValidationError;
 use thiserror::Error;
 
-use crate::models::ForeignKeyType;
+use crate::models::{ErrorDetail, ErrorResponse, ForeignKeyType, ForeignKind, NamespacePolicy};
 
 #[derive(Error, Debug)]
 pub enum DawnStoreError {
@@ -23,6 +23,95 @@ pub enum DawnStoreError {
     ForeignKeyNotFound(String),
     #[error("No Schema for object version: {api_version} kind: {kind} found")]
     NoSchemaForObjectFound { api_version: String, kind: String },
+    #[error("No status schema for object version: {api_version} kind: {kind} found")]
+    NoStatusSchemaForObjectFound { api_version: String, kind: String },
+    #[error("Object {0} not found")]
+    ObjectNotFound(String),
+    #[error("Spec of {kind} uses the reserved field {field}")]
+    ReservedSpecField { kind: String, field: String },
+    #[error("Owner {owner} of {object} not found")]
+    OwnerNotFound { object: String, owner: String },
+    #[error("Owner {owner} of {object} is in a different namespace")]
+    OwnerInDifferentNamespace { object: String, owner: String },
+    #[error("Lease {lease} is held by {holder}")]
+    LeaseHeldByOther { lease: String, holder: String },
+    #[error("Lease {lease} is not held by {holder}")]
+    LeaseNotHeld { lease: String, holder: String },
+    #[error("Lease {0} can only be changed through the lease endpoints")]
+    LeaseNotWritable(String),
+    #[error("Lease duration {0} is not between 1 and {max} seconds", max = dawnstore_lib::MAX_LEASE_DURATION_SECONDS)]
+    InvalidLeaseDuration(i64),
+    #[error("Object {object} already exists as {api_version}/{kind}")]
+    BuiltInObjectTaken {
+        object: String,
+        api_version: String,
+        kind: String,
+    },
+    #[error("Finalizer {finalizer} can not be added to {object} while it is being deleted")]
+    FinalizerAddedDuringDeletion { object: String, finalizer: String },
+    #[error("Object {object} can not be deleted, it is still referenced by {referenced_by:?}")]
+    DeletionBlockedByForeignKey {
+        object: String,
+        referenced_by: Vec<String>,
+    },
+    #[error("Error during patch: {0}")]
+    PatchError(#[from] json_patch::PatchError),
+    #[error("Server-side apply requires a field manager")]
+    FieldManagerRequired,
+    #[error("Apply of {object} conflicts with fields of other managers: {}", conflicts.join(", "))]
+    FieldManagerConflict {
+        object: String,
+        conflicts: Vec<String>,
+    },
+    #[error("Invalid label selector {0}")]
+    InvalidLabelSelector(String),
+    #[error("Prune requires a label selector or an applied set")]
+    PruneWithoutSelector,
+    #[error("Precondition of {object} failed: {reason}")]
+    PreconditionFailed { object: String, reason: String },
+    #[error("Operation {index} of the transaction failed: {error}")]
+    TransactionOperationFailed {
+        index: usize,
+        error: Box<DawnStoreError>,
+    },
+    #[error("Invalid path {path} of unique constraint {constraint}")]
+    InvalidUniqueConstraintPath { constraint: String, path: String },
+    #[error("Object {object} has immutable fields which can not change: {}", paths.join(", "))]
+    ImmutableFieldChanged {
+        object: String,
+        /// json pointers of the changed fields in the spec
+        paths: Vec<String>,
+    },
+    #[error("Invalid validation rule {rule}: {error}")]
+    InvalidValidationRule { rule: String, error: String },
+    #[error("Object {object} failed validation rule {rule} at {path}: {message}")]
+    ValidationRuleFailed {
+        object: String,
+        rule: String,
+        path: String,
+        message: String,
+    },
+    #[error("Invalid spec index path {0}")]
+    InvalidSpecIndexPath(String),
+    #[error("Invalid foreign key path {0}")]
+    InvalidForeignKeyPath(String),
+    #[error("Foreign key {foreign_key_path} forms a cycle: {}", cycle.join(" -> "))]
+    ForeignKeyCycle {
+        foreign_key_path: String,
+        cycle: Vec<String>,
+    },
+    #[error("Invalid field selector {0}")]
+    InvalidFieldSelector(String),
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+    #[error(
+        "Unique constraint {constraint} violated, {object} has the same values as {conflicting}"
+    )]
+    UniqueConstraintViolation {
+        constraint: String,
+        object: String,
+        conflicting: String,
+    },
     #[error("Database Error: {0}")]
     DatabaseError(#[from] sqlx::Error),
     #[error("Error during jsonshema creation: {0}")]
@@ -34,44 +123,327 @@ pub enum DawnStoreError {
         name: String,
         validation_error: ValidationError<'static>,
     },
+    #[error("Error during status validation of {api_version}/{kind}/{name}: {validation_error}")]
+    ObjectStatusValidationError {
+        api_version: String,
+        kind: String,
+        name: String,
+        validation_error: ValidationError<'static>,
+    },
     #[error(
-        "Error missing foreign key field {api_version}/{kind}/{name}: {foreign_key_path} type: {foreign_key_type:?}"
+        "Error missing foreign key field {api_version}/{kind}/{name}: {pointer} type: {foreign_key_type:?}"
     )]
     ObjectValidationMissingForeignKeyEntry {
         api_version: String,
         kind: String,
         name: String,
-        foreign_key_path: String,
+        /// json pointer of the reference in the spec, e.g. `/slots/2/item`
+        pointer: String,
         foreign_key_type: ForeignKeyType,
     },
 
     #[error(
-        "Error wrong foreign key field {api_version}/{kind}/{name}: {foreign_key_path} type: {foreign_key_type:?} value: {value}"
+        "Error wrong foreign key field {api_version}/{kind}/{name}: {pointer} type: {foreign_key_type:?} value: {value}"
     )]
     ObjectValidationWrongForeignKeyEntryFormat {
         api_version: String,
         kind: String,
         name: String,
-        foreign_key_path: String,
+        /// json pointer of the reference in the spec, e.g. `/slots/2/item`
+        pointer: String,
         foreign_key_type: ForeignKeyType,
         value: String,
     },
     #[error(
-        "Error wrong foreign key kind {api_version}/{kind}/{name}: {foreign_key_path} type: {foreign_key_type:?} value: {value}"
+        "Error wrong foreign key kind {api_version}/{kind}/{name}: {pointer} type: {foreign_key_type:?} value: {value} allowed: {}",
+        .allowed_kinds.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")
     )]
     ObjectValidationWrongForeignKeyEntryKind {
         api_version: String,
         kind: String,
         name: String,
-        foreign_key_path: String,
+        /// json pointer of the reference in the spec, e.g. `/slots/2/item`
+        pointer: String,
         foreign_key_type: ForeignKeyType,
+        /// the value, for a wrong api version the one of the referenced object
         value: String,
+        allowed_kinds: Vec<ForeignKind>,
     },
-    #[error("Error foreign key {api_version}/{kind}/{name}: value: {value} not found")]
+    #[error("Error foreign key {api_version}/{kind}/{name}: {pointer} value: {value} not found")]
     ObjectValidationForeignKeyNotFound {
         api_version: String,
         kind: String,
         name: String,
+        /// json pointer of the reference in the spec, e.g. `/slots/2/item`
+        pointer: String,
         value: String,
     },
+    #[error(
+        "Error foreign key {api_version}/{kind}/{name}: {pointer} value: {value} violates the namespace policy: {policy}"
+    )]
+    ObjectValidationForeignKeyNamespaceNotAllowed {
+        api_version: String,
+        kind: String,
+        name: String,
+        /// json pointer of the reference in the spec, e.g. `/slots/2/item`
+        pointer: String,
+        value: String,
+        policy: NamespacePolicy,
+    },
+    #[error("Error foreign key properties {api_version}/{kind}/{name}: {pointer}: {message}")]
+    ObjectValidationForeignKeyPropertiesError {
+        api_version: String,
+        kind: String,
+        name: String,
+        /// json pointer of the reference in the spec followed by the one
+        /// within the properties
+        pointer: String,
+        message: String,
+    },
+    #[error("Validation failed: {}", .0.iter().map(|x| x.to_string()).collect::<Vec<_>>().join("; "))]
+    ValidationFailed(Vec<ObjectError>),
+}
+
+/// an error of a single object of a request, collected with the errors of
+/// the other objects
+#[derive(Error, Debug)]
+#[error("{object}: {error}")]
+pub struct ObjectError {
+    pub object: String,
+    pub error: DawnStoreError,
+}
+
+fn dot_path_pointer(path: &str) -> String {
+    format!("/{}", path.replace('.', "/"))
+}
+
+impl DawnStoreError {
+    /// errors of the data sent by the client are collected over all objects,
+    /// everything else aborts the request
+    pub fn is_validation_error(&self) -> bool {
+        matches!(
+            self,
+            Self::ObjectValidationError { .. }
+                | Self::ObjectValidationMissingForeignKeyEntry { .. }
+                | Self::ObjectValidationWrongForeignKeyEntryFormat { .. }
+                | Self::ObjectValidationWrongForeignKeyEntryKind { .. }
+                | Self::ObjectValidationForeignKeyNotFound { .. }
+                | Self::ObjectValidationForeignKeyPropertiesError { .. }
+                | Self::ObjectValidationForeignKeyNamespaceNotAllowed { .. }
+                | Self::ValidationRuleFailed { .. }
+                | Self::ImmutableFieldChanged { .. }
+                | Self::OwnerNotFound { .. }
+                | Self::OwnerInDifferentNamespace { .. }
+                | Self::UniqueConstraintViolation { .. }
+                | Self::ValidationFailed(_)
+        )
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidRootInputObject => "InvalidRootInputObject",
+            Self::InternalServerError(_) => "InternalServerError",
+            Self::InvalidInputObjectMissingKindField => "MissingKindField",
+            Self::InvalidInputObjectMissingListFieldOfList => "MissingListField",
+            Self::DeserialisationError(_) => "DeserialisationError",
+            Self::KindMissingInObject => "KindMissing",
+            Self::ApiVersionMissingInObject => "ApiVersionMissing",
+            Self::ForeignKeyNotFound(_) => "ForeignKeyNotFound",
+            Self::NoSchemaForObjectFound { .. } => "SchemaNotFound",
+            Self::NoStatusSchemaForObjectFound { .. } => "StatusSchemaNotFound",
+            Self::ObjectNotFound(_) => "ObjectNotFound",
+            Self::OwnerNotFound { .. } => "OwnerNotFound",
+            Self::OwnerInDifferentNamespace { .. } => "OwnerInDifferentNamespace",
+            Self::LeaseHeldByOther { .. } => "LeaseHeldByOther",
+            Self::LeaseNotHeld { .. } => "LeaseNotHeld",
+            Self::LeaseNotWritable(_) => "LeaseNotWritable",
+            Self::InvalidLeaseDuration(_) => "InvalidLeaseDuration",
+            Self::BuiltInObjectTaken { .. } => "BuiltInObjectTaken",
+            Self::FinalizerAddedDuringDeletion { .. } => "FinalizerAddedDuringDeletion",
+            Self::DeletionBlockedByForeignKey { .. } => "DeletionBlocked",
+            Self::PatchError(_) => "PatchFailed",
+            Self::FieldManagerRequired => "FieldManagerRequired",
+            Self::FieldManagerConflict { .. } => "FieldManagerConflict",
+            Self::InvalidLabelSelector(_) => "InvalidLabelSelector",
+            Self::PruneWithoutSelector => "PruneWithoutSelector",
+            Self::PreconditionFailed { .. } => "PreconditionFailed",
+            Self::TransactionOperationFailed { .. } => "TransactionOperationFailed",
+            Self::InvalidUniqueConstraintPath { .. } => "InvalidUniqueConstraintPath",
+            Self::ImmutableFieldChanged { .. } => "ImmutableFieldChanged",
+            Self::InvalidValidationRule { .. } => "InvalidValidationRule",
+            Self::ValidationRuleFailed { .. } => "ValidationRuleFailed",
+            Self::InvalidSpecIndexPath(_) => "InvalidSpecIndexPath",
+            Self::InvalidForeignKeyPath(_) => "InvalidForeignKeyPath",
+            Self::ReservedSpecField { .. } => "ReservedSpecField",
+            Self::InvalidFieldSelector(_) => "InvalidFieldSelector",
+            Self::InvalidQuery(_) => "InvalidQuery",
+            Self::ForeignKeyCycle { .. } => "ForeignKeyCycle",
+            Self::UniqueConstraintViolation { .. } => "UniqueConstraintViolation",
+            Self::DatabaseError(_) => "DatabaseError",
+            Self::JsonSchemaValidatorCreationError(_) => "InvalidJsonSchema",
+            Self::ObjectValidationError { .. } => "SchemaViolation",
+            Self::ObjectStatusValidationError { .. } => "StatusSchemaViolation",
+            Self::ObjectValidationMissingForeignKeyEntry { .. } => "MissingForeignKey",
+            Self::ObjectValidationWrongForeignKeyEntryFormat { .. } => "InvalidForeignKeyFormat",
+            Self::ObjectValidationWrongForeignKeyEntryKind { .. } => "InvalidForeignKeyKind",
+            Self::ObjectValidationForeignKeyNotFound { .. } => "ForeignKeyNotFound",
+            Self::ObjectValidationForeignKeyPropertiesError { .. } => "InvalidForeignKeyProperties",
+            Self::ObjectValidationForeignKeyNamespaceNotAllowed { .. } => {
+                "ForeignKeyNamespaceNotAllowed"
+            }
+            Self::ValidationFailed(_) => "ValidationFailed",
+        }
+    }
+
+    /// http status of the error, 400 for malformed requests, 404 for missing
+    /// objects, 409 for conflicts with the stored state and 422 for objects
+    /// which are well formed but invalid
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::ObjectNotFound(_)
+            | Self::NoSchemaForObjectFound { .. }
+            | Self::NoStatusSchemaForObjectFound { .. } => 404,
+            Self::LeaseHeldByOther { .. }
+            | Self::BuiltInObjectTaken { .. }
+            | Self::LeaseNotHeld { .. }
+            | Self::FinalizerAddedDuringDeletion { .. }
+            | Self::DeletionBlockedByForeignKey { .. }
+            | Self::FieldManagerConflict { .. }
+            | Self::PreconditionFailed { .. }
+            | Self::UniqueConstraintViolation { .. } => 409,
+            Self::ObjectValidationError { .. }
+            | Self::ObjectStatusValidationError { .. }
+            | Self::ObjectValidationMissingForeignKeyEntry { .. }
+            | Self::ObjectValidationWrongForeignKeyEntryFormat { .. }
+            | Self::ObjectValidationWrongForeignKeyEntryKind { .. }
+            | Self::ObjectValidationForeignKeyNotFound { .. }
+            | Self::ObjectValidationForeignKeyPropertiesError { .. }
+            | Self::ObjectValidationForeignKeyNamespaceNotAllowed { .. }
+            | Self::ForeignKeyNotFound(_)
+            | Self::OwnerNotFound { .. }
+            | Self::OwnerInDifferentNamespace { .. }
+            | Self::ImmutableFieldChanged { .. }
+            | Self::ValidationRuleFailed { .. }
+            | Self::ForeignKeyCycle { .. } => 422,
+            // the common status of the errors, e.g. 409 for unique violations only
+            Self::ValidationFailed(errors) => {
+                let mut codes = errors.iter().map(|x| x.error.status_code());
+                match codes.next() {
+                    Some(first) if codes.all(|x| x == first) => first,
+                    _ => 422,
+                }
+            }
+            Self::TransactionOperationFailed { error, .. } => error.status_code(),
+            Self::InternalServerError(_)
+            | Self::DatabaseError(_)
+            | Self::JsonSchemaValidatorCreationError(_)
+            | Self::InvalidUniqueConstraintPath { .. }
+            | Self::InvalidValidationRule { .. }
+            | Self::InvalidSpecIndexPath(_)
+            | Self::InvalidForeignKeyPath(_)
+            | Self::ReservedSpecField { .. } => 500,
+            _ => 400,
+        }
+    }
+
+    /// the single errors with the objects they belong to, the object is
+    /// only known for collected errors and errors naming it
+    pub fn details(&self) -> Vec<ErrorDetail> {
+        let detail = |object: Option<&str>, pointer: Option<String>| ErrorDetail {
+            object: object.map(|x| x.to_owned()),
+            pointer,
+            message: self.to_string(),
+            code: self.code().to_owned(),
+        };
+        match self {
+            Self::ValidationFailed(errors) => errors
+                .iter()
+                .flat_map(|x| {
+                    x.error.details().into_iter().map(|mut d| {
+                        d.object.get_or_insert_with(|| x.object.clone());
+                        d
+                    })
+                })
+                .collect(),
+            Self::TransactionOperationFailed { error, .. } => error.details(),
+            Self::ObjectValidationError {
+                validation_error, ..
+            }
+            | Self::ObjectStatusValidationError {
+                validation_error, ..
+            } => vec![ErrorDetail {
+                object: None,
+                pointer: Some(validation_error.instance_path().to_string()),
+                message: validation_error.to_string(),
+                code: self.code().to_owned(),
+            }],
+            Self::ObjectValidationMissingForeignKeyEntry { pointer, .. }
+            | Self::ObjectValidationWrongForeignKeyEntryFormat { pointer, .. }
+            | Self::ObjectValidationWrongForeignKeyEntryKind { pointer, .. }
+            | Self::ObjectValidationForeignKeyNotFound { pointer, .. }
+            | Self::ObjectValidationForeignKeyNamespaceNotAllowed { pointer, .. } => {
+                vec![detail(None, Some(pointer.clone()))]
+            }
+            Self::ObjectValidationForeignKeyPropertiesError {
+                pointer, message, ..
+            } => vec![ErrorDetail {
+                object: None,
+                pointer: Some(pointer.clone()),
+                message: message.clone(),
+                code: self.code().to_owned(),
+            }],
+            Self::ForeignKeyCycle {
+                foreign_key_path,
+                cycle,
+            } => vec![detail(
+                cycle.first().map(|x| x.as_str()),
+                // a path through arrays has no single place
+                (!foreign_key_path.contains('[')).then(|| dot_path_pointer(foreign_key_path)),
+            )],
+            Self::ValidationRuleFailed {
+                object,
+                path,
+                message,
+                ..
+            } => vec![ErrorDetail {
+                object: Some(object.clone()),
+                pointer: Some(path.strip_prefix("spec").unwrap_or(path).replace('.', "/")),
+                message: message.clone(),
+                code: self.code().to_owned(),
+            }],
+            Self::ImmutableFieldChanged { object, paths } => paths
+                .iter()
+                .map(|x| ErrorDetail {
+                    object: Some(object.clone()),
+                    pointer: Some(x.clone()),
+                    message: format!("field {x} is immutable"),
+                    code: self.code().to_owned(),
+                })
+                .collect(),
+            Self::OwnerNotFound { object, .. } | Self::OwnerInDifferentNamespace { object, .. } => {
+                vec![detail(Some(object), Some("/owners".to_string()))]
+            }
+            Self::FinalizerAddedDuringDeletion { object, .. } => {
+                vec![detail(Some(object), Some("/finalizers".to_string()))]
+            }
+            Self::DeletionBlockedByForeignKey { object, .. }
+            | Self::FieldManagerConflict { object, .. }
+            | Self::PreconditionFailed { object, .. }
+            | Self::UniqueConstraintViolation { object, .. } => vec![detail(Some(object), None)],
+            Self::ObjectNotFound(object)
+            | Self::LeaseNotWritable(object)
+            | Self::BuiltInObjectTaken { object, .. } => {
+                vec![detail(Some(object), None)]
+            }
+            _ => vec![detail(None, None)],
+        }
+    }
+
+    pub fn to_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            code: self.code().to_owned(),
+            message: self.to_string(),
+            errors: self.details(),
+        }
+    }
 }
@@ -0,0 +1,809 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde_json::Value;
+use thiserror::Error;
+
+/// a small expression language for validation rules modelled after cel
+///
+/// supported are literals, lists, member and index access, the operators
+/// `! - * / % + < <= > >= == != in && || ?:`, the functions `has`, `size`,
+/// `now`, `timestamp`, `int`, `double` and `string` and the methods `size`,
+/// `contains`, `startsWith`, `endsWith`, `lowerAscii`, `upperAscii`, `all`
+/// and `exists`. timestamps have the members `year`, `month`, `day`, `hour`,
+/// `minute` and `second`.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    expr: Expr,
+}
+
+#[derive(Error, Debug)]
+#[error("{0}")]
+pub struct ExpressionError(String);
+
+type Result<T> = std::result::Result<T, ExpressionError>;
+
+fn error<T>(msg: impl Into<String>) -> Result<T> {
+    Err(ExpressionError(msg.into()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Val {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Val>),
+    Map(BTreeMap<String, Val>),
+    Timestamp(DateTime<Utc>),
+}
+
+impl From<&Value> for Val {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Val::Null,
+            Value::Bool(x) => Val::Bool(*x),
+            Value::Number(x) => match x.as_i64() {
+                Some(x) => Val::Int(x),
+                None => Val::Float(x.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(x) => Val::String(x.clone()),
+            Value::Array(x) => Val::List(x.iter().map(Val::from).collect()),
+            Value::Object(x) => Val::Map(x.iter().map(|(k, v)| (k.clone(), v.into())).collect()),
+        }
+    }
+}
+
+impl Val {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Val::Null => "null",
+            Val::Bool(_) => "bool",
+            Val::Int(_) => "int",
+            Val::Float(_) => "double",
+            Val::String(_) => "string",
+            Val::List(_) => "list",
+            Val::Map(_) => "map",
+            Val::Timestamp(_) => "timestamp",
+        }
+    }
+
+    fn as_bool(&self) -> Result<bool> {
+        match self {
+            Val::Bool(x) => Ok(*x),
+            x => error(format!("expected bool, got {}", x.type_name())),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str> {
+        match self {
+            Val::String(x) => Ok(x),
+            x => error(format!("expected string, got {}", x.type_name())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 22] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", "[", "]",
+    ".", ",", "?", ":",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let float = i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit();
+            if float {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            tokens.push(match float {
+                true => Token::Float(text.parse().map_err(|_| ExpressionError(text))?),
+                false => Token::Int(text.parse().map_err(|_| ExpressionError(text))?),
+            });
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let ident = chars[start..i].iter().collect::<String>();
+            tokens.push(match ident.as_str() {
+                "in" => Token::Sym("in"),
+                _ => Token::Ident(ident),
+            });
+        } else if c == '\'' || c == '"' {
+            i += 1;
+            let mut text = String::new();
+            loop {
+                let Some(&x) = chars.get(i) else {
+                    return error("unterminated string");
+                };
+                i += 1;
+                match x {
+                    x if x == c => break,
+                    '\\' => {
+                        let Some(&escaped) = chars.get(i) else {
+                            return error("unterminated string");
+                        };
+                        i += 1;
+                        text.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            x => x,
+                        });
+                    }
+                    x => text.push(x),
+                }
+            }
+            tokens.push(Token::Str(text));
+        } else {
+            let rest = chars[i..].iter().take(2).collect::<String>();
+            let Some(sym) = SYMBOLS.iter().find(|x| rest.starts_with(**x)) else {
+                return error(format!("unexpected character {c}"));
+            };
+            i += sym.len();
+            tokens.push(Token::Sym(sym));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Val),
+    Ident(String),
+    List(Vec<Expr>),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Call {
+        target: Option<Box<Expr>>,
+        name: String,
+        args: Vec<Expr>,
+    },
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, sym: &str) -> bool {
+        if matches!(self.tokens.get(self.pos), Some(Token::Sym(x)) if *x == sym) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_any(&mut self, syms: &[&'static str]) -> Option<&'static str> {
+        syms.iter().copied().find(|x| self.eat(x))
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<()> {
+        match self.eat(sym) {
+            true => Ok(()),
+            false => error(format!("expected {sym} at token {}", self.pos)),
+        }
+    }
+
+    fn ternary(&mut self) -> Result<Expr> {
+        let cond = self.or()?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let a = self.ternary()?;
+        self.expect(":")?;
+        let b = self.ternary()?;
+        Ok(Expr::Ternary(Box::new(cond), Box::new(a), Box::new(b)))
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[&'static str],
+        next: fn(&mut Self) -> Result<Expr>,
+    ) -> Result<Expr> {
+        let mut left = next(self)?;
+        while let Some(op) = self.eat_any(ops) {
+            let right = next(self)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        self.binary(&["&&"], Self::relation)
+    }
+
+    fn relation(&mut self) -> Result<Expr> {
+        self.binary(&["==", "!=", "<=", ">=", "<", ">", "in"], Self::addition)
+    }
+
+    fn addition(&mut self) -> Result<Expr> {
+        self.binary(&["+", "-"], Self::multiplication)
+    }
+
+    fn multiplication(&mut self) -> Result<Expr> {
+        self.binary(&["*", "/", "%"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.eat_any(&["!", "-"]) {
+            Some(op) => Ok(Expr::Unary(op, Box::new(self.unary()?))),
+            None => self.postfix(),
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.eat(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.ternary()?);
+            if self.eat(")") {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Ident(x)) => {
+                self.pos += 1;
+                Ok(x.clone())
+            }
+            _ => error(format!("expected identifier at token {}", self.pos)),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(".") {
+                let name = self.ident()?;
+                expr = match self.eat("(") {
+                    true => Expr::Call {
+                        target: Some(Box::new(expr)),
+                        name,
+                        args: self.arguments()?,
+                    },
+                    false => Expr::Member(Box::new(expr), name),
+                };
+            } else if self.eat("[") {
+                let index = self.ternary()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return error("unexpected end of expression");
+        };
+        self.pos += 1;
+        Ok(match token {
+            Token::Int(x) => Expr::Literal(Val::Int(x)),
+            Token::Float(x) => Expr::Literal(Val::Float(x)),
+            Token::Str(x) => Expr::Literal(Val::String(x)),
+            Token::Ident(x) => match x.as_str() {
+                "true" => Expr::Literal(Val::Bool(true)),
+                "false" => Expr::Literal(Val::Bool(false)),
+                "null" => Expr::Literal(Val::Null),
+                _ if self.eat("(") => Expr::Call {
+                    target: None,
+                    name: x,
+                    args: self.arguments()?,
+                },
+                _ => Expr::Ident(x),
+            },
+            Token::Sym("(") => {
+                let expr = self.ternary()?;
+                self.expect(")")?;
+                expr
+            }
+            Token::Sym("[") => {
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    items.push(self.ternary()?);
+                    if !self.eat(",") {
+                        self.expect("]")?;
+                        break;
+                    }
+                }
+                Expr::List(items)
+            }
+            Token::Sym(x) => return error(format!("unexpected {x}")),
+        })
+    }
+}
+
+fn compare(a: &Val, b: &Val) -> Result<std::cmp::Ordering> {
+    let ordering = match (a, b) {
+        (Val::Int(a), Val::Int(b)) => Some(a.cmp(b)),
+        (Val::Int(a), Val::Float(b)) => (*a as f64).partial_cmp(b),
+        (Val::Float(a), Val::Int(b)) => a.partial_cmp(&(*b as f64)),
+        (Val::Float(a), Val::Float(b)) => a.partial_cmp(b),
+        (Val::String(a), Val::String(b)) => Some(a.cmp(b)),
+        (Val::Bool(a), Val::Bool(b)) => Some(a.cmp(b)),
+        (Val::Timestamp(a), Val::Timestamp(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match ordering {
+        Some(x) => Ok(x),
+        None => error(format!(
+            "can not compare {} with {}",
+            a.type_name(),
+            b.type_name()
+        )),
+    }
+}
+
+fn equals(a: &Val, b: &Val) -> bool {
+    match (a, b) {
+        (Val::Int(_) | Val::Float(_), Val::Int(_) | Val::Float(_)) => {
+            compare(a, b).is_ok_and(|x| x.is_eq())
+        }
+        _ => a == b,
+    }
+}
+
+fn arithmetic(op: &str, a: Val, b: Val) -> Result<Val> {
+    let overflow = || ExpressionError(format!("integer overflow in {op}"));
+    match (op, a, b) {
+        ("+", Val::String(a), Val::String(b)) => Ok(Val::String(a + &b)),
+        ("+", Val::List(mut a), Val::List(b)) => {
+            a.extend(b);
+            Ok(Val::List(a))
+        }
+        ("/" | "%", Val::Int(_), Val::Int(0)) => error("division by zero"),
+        (op, Val::Int(a), Val::Int(b)) => match op {
+            "+" => a.checked_add(b),
+            "-" => a.checked_sub(b),
+            "*" => a.checked_mul(b),
+            "/" => a.checked_div(b),
+            _ => a.checked_rem(b),
+        }
+        .map(Val::Int)
+        .ok_or_else(overflow),
+        (op, a @ (Val::Int(_) | Val::Float(_)), b @ (Val::Int(_) | Val::Float(_))) if op != "%" => {
+            let float = |x: Val| match x {
+                Val::Int(x) => x as f64,
+                Val::Float(x) => x,
+                _ => unreachable!("only numbers are matched"),
+            };
+            let (a, b) = (float(a), float(b));
+            Ok(Val::Float(match op {
+                "+" => a + b,
+                "-" => a - b,
+                "*" => a * b,
+                _ => a / b,
+            }))
+        }
+        (op, a, b) => error(format!(
+            "operator {op} is not defined for {} and {}",
+            a.type_name(),
+            b.type_name()
+        )),
+    }
+}
+
+fn size(value: &Val) -> Result<Val> {
+    Ok(Val::Int(match value {
+        Val::String(x) => x.chars().count(),
+        Val::List(x) => x.len(),
+        Val::Map(x) => x.len(),
+        x => return error(format!("size is not defined for {}", x.type_name())),
+    } as i64))
+}
+
+struct Evaluator {
+    vars: Vec<(String, Val)>,
+}
+
+impl Evaluator {
+    fn eval(&mut self, expr: &Expr) -> Result<Val> {
+        match expr {
+            Expr::Literal(x) => Ok(x.clone()),
+            Expr::Ident(name) => match self.vars.iter().rev().find(|(n, _)| n == name) {
+                Some((_, x)) => Ok(x.clone()),
+                None => error(format!("undeclared reference to {name}")),
+            },
+            Expr::List(items) => Ok(Val::List(
+                items.iter().map(|x| self.eval(x)).collect::<Result<_>>()?,
+            )),
+            Expr::Member(target, name) => match self.eval(target)? {
+                Val::Map(mut x) => match x.remove(name) {
+                    Some(x) => Ok(x),
+                    None => error(format!("no such key: {name}")),
+                },
+                Val::Timestamp(x) => Ok(Val::Int(match name.as_str() {
+                    "year" => x.year() as i64,
+                    "month" => x.month() as i64,
+                    "day" => x.day() as i64,
+                    "hour" => x.hour() as i64,
+                    "minute" => x.minute() as i64,
+                    "second" => x.second() as i64,
+                    _ => return error(format!("timestamp has no member {name}")),
+                })),
+                x => error(format!("{} has no member {name}", x.type_name())),
+            },
+            Expr::Index(target, index) => match (self.eval(target)?, self.eval(index)?) {
+                (Val::List(mut x), Val::Int(i)) => match usize::try_from(i) {
+                    Ok(i) if i < x.len() => Ok(x.swap_remove(i)),
+                    _ => error(format!("index {i} out of range")),
+                },
+                (Val::Map(mut x), Val::String(key)) => match x.remove(&key) {
+                    Some(x) => Ok(x),
+                    None => error(format!("no such key: {key}")),
+                },
+                (a, b) => error(format!(
+                    "can not index {} with {}",
+                    a.type_name(),
+                    b.type_name()
+                )),
+            },
+            Expr::Unary(op, x) => match (*op, self.eval(x)?) {
+                ("!", x) => Ok(Val::Bool(!x.as_bool()?)),
+                (_, Val::Int(x)) => x
+                    .checked_neg()
+                    .map(Val::Int)
+                    .ok_or_else(|| ExpressionError("integer overflow in negation".to_string())),
+                (_, Val::Float(x)) => Ok(Val::Float(-x)),
+                (_, x) => error(format!("can not negate {}", x.type_name())),
+            },
+            Expr::Binary("&&", a, b) => match self.eval(a)?.as_bool()? {
+                false => Ok(Val::Bool(false)),
+                true => Ok(Val::Bool(self.eval(b)?.as_bool()?)),
+            },
+            Expr::Binary("||", a, b) => match self.eval(a)?.as_bool()? {
+                true => Ok(Val::Bool(true)),
+                false => Ok(Val::Bool(self.eval(b)?.as_bool()?)),
+            },
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                match *op {
+                    "==" => Ok(Val::Bool(equals(&a, &b))),
+                    "!=" => Ok(Val::Bool(!equals(&a, &b))),
+                    "<" => Ok(Val::Bool(compare(&a, &b)?.is_lt())),
+                    "<=" => Ok(Val::Bool(compare(&a, &b)?.is_le())),
+                    ">" => Ok(Val::Bool(compare(&a, &b)?.is_gt())),
+                    ">=" => Ok(Val::Bool(compare(&a, &b)?.is_ge())),
+                    "in" => match b {
+                        Val::List(x) => Ok(Val::Bool(x.iter().any(|x| equals(x, &a)))),
+                        Val::Map(x) => Ok(Val::Bool(x.contains_key(a.as_str()?))),
+                        x => error(format!("in is not defined for {}", x.type_name())),
+                    },
+                    op => arithmetic(op, a, b),
+                }
+            }
+            Expr::Ternary(cond, a, b) => match self.eval(cond)?.as_bool()? {
+                true => self.eval(a),
+                false => self.eval(b),
+            },
+            Expr::Call { target, name, args } => self.call(target.as_deref(), name, args),
+        }
+    }
+
+    fn call(&mut self, target: Option<&Expr>, name: &str, args: &[Expr]) -> Result<Val> {
+        match (target, name, args) {
+            (None, "has", [Expr::Member(target, field)]) => match self.eval(target)? {
+                Val::Map(x) => Ok(Val::Bool(x.contains_key(field))),
+                x => error(format!("has is not defined for {}", x.type_name())),
+            },
+            (None, "has", _) => error("has expects a field selection like has(self.field)"),
+            (Some(target), "all" | "exists", [Expr::Ident(var), predicate]) => {
+                let items = match self.eval(target)? {
+                    Val::List(x) => x,
+                    Val::Map(x) => x.into_keys().map(Val::String).collect(),
+                    x => return error(format!("{name} is not defined for {}", x.type_name())),
+                };
+                let all = name == "all";
+                for item in items {
+                    self.vars.push((var.clone(), item));
+                    let result = self.eval(predicate).and_then(|x| x.as_bool());
+                    self.vars.pop();
+                    if result? != all {
+                        return Ok(Val::Bool(!all));
+                    }
+                }
+                Ok(Val::Bool(all))
+            }
+            (Some(_), "all" | "exists", _) => {
+                error(format!("{name} expects a variable and a predicate"))
+            }
+            _ => {
+                let target = target.map(|x| self.eval(x)).transpose()?;
+                let args = args
+                    .iter()
+                    .map(|x| self.eval(x))
+                    .collect::<Result<Vec<_>>>()?;
+                function(target, name, args)
+            }
+        }
+    }
+}
+
+fn function(target: Option<Val>, name: &str, args: Vec<Val>) -> Result<Val> {
+    match (target.as_ref(), name, args.as_slice()) {
+        (None, "now", []) => Ok(Val::Timestamp(Utc::now())),
+        (None, "timestamp", [Val::String(x)]) => DateTime::parse_from_rfc3339(x)
+            .map(|x| Val::Timestamp(x.with_timezone(&Utc)))
+            .map_err(|e| ExpressionError(format!("invalid timestamp {x}: {e}"))),
+        (None, "size", [x]) | (Some(x), "size", []) => size(x),
+        (None, "int", [x]) => match x {
+            Val::Int(x) => Ok(Val::Int(*x)),
+            Val::Float(x) => Ok(Val::Int(*x as i64)),
+            Val::String(x) => x
+                .parse()
+                .map(Val::Int)
+                .map_err(|_| ExpressionError(format!("invalid int {x}"))),
+            Val::Timestamp(x) => Ok(Val::Int(x.timestamp())),
+            x => error(format!("can not convert {} to int", x.type_name())),
+        },
+        (None, "double", [x]) => match x {
+            Val::Int(x) => Ok(Val::Float(*x as f64)),
+            Val::Float(x) => Ok(Val::Float(*x)),
+            Val::String(x) => x
+                .parse()
+                .map(Val::Float)
+                .map_err(|_| ExpressionError(format!("invalid double {x}"))),
+            x => error(format!("can not convert {} to double", x.type_name())),
+        },
+        (None, "string", [x]) => match x {
+            Val::String(x) => Ok(Val::String(x.clone())),
+            Val::Int(x) => Ok(Val::String(x.to_string())),
+            Val::Float(x) => Ok(Val::String(x.to_string())),
+            Val::Bool(x) => Ok(Val::String(x.to_string())),
+            Val::Timestamp(x) => Ok(Val::String(x.to_rfc3339())),
+            x => error(format!("can not convert {} to string", x.type_name())),
+        },
+        (Some(Val::String(x)), "contains", [Val::String(y)]) => Ok(Val::Bool(x.contains(y))),
+        (Some(Val::List(x)), "contains", [y]) => Ok(Val::Bool(x.iter().any(|x| equals(x, y)))),
+        (Some(Val::String(x)), "startsWith", [Val::String(y)]) => {
+            Ok(Val::Bool(x.starts_with(y.as_str())))
+        }
+        (Some(Val::String(x)), "endsWith", [Val::String(y)]) => {
+            Ok(Val::Bool(x.ends_with(y.as_str())))
+        }
+        (Some(Val::String(x)), "lowerAscii", []) => Ok(Val::String(x.to_ascii_lowercase())),
+        (Some(Val::String(x)), "upperAscii", []) => Ok(Val::String(x.to_ascii_uppercase())),
+        (Some(x), name, _) => error(format!(
+            "no method {name} with {} arguments on {}",
+            args.len(),
+            x.type_name()
+        )),
+        (None, name, _) => error(format!("no function {name} with {} arguments", args.len())),
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> std::result::Result<Self, ExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let expr = parser.ternary()?;
+        if parser.pos != parser.tokens.len() {
+            return error(format!("unexpected token at {}", parser.pos));
+        }
+        Ok(Self { expr })
+    }
+
+    /// transition rules reference `oldSelf` and are only checked on updates
+    pub fn is_transition_rule(&self) -> bool {
+        fn uses_old_self(expr: &Expr) -> bool {
+            match expr {
+                Expr::Ident(x) => x == "oldSelf",
+                Expr::Literal(_) => false,
+                Expr::List(x) => x.iter().any(uses_old_self),
+                Expr::Member(x, _) | Expr::Unary(_, x) => uses_old_self(x),
+                Expr::Index(a, b) | Expr::Binary(_, a, b) => uses_old_self(a) || uses_old_self(b),
+                Expr::Call { target, args, .. } => {
+                    target.as_deref().is_some_and(uses_old_self) || args.iter().any(uses_old_self)
+                }
+                Expr::Ternary(a, b, c) => uses_old_self(a) || uses_old_self(b) || uses_old_self(c),
+            }
+        }
+        uses_old_self(&self.expr)
+    }
+
+    pub fn evaluate(
+        &self,
+        value: &Value,
+        old_value: Option<&Value>,
+    ) -> std::result::Result<bool, ExpressionError> {
+        let mut vars = vec![("self".to_string(), Val::from(value))];
+        if let Some(x) = old_value {
+            vars.push(("oldSelf".to_string(), Val::from(x)));
+        }
+        match (Evaluator { vars }).eval(&self.expr)? {
+            Val::Bool(x) => Ok(x),
+            x => error(format!(
+                "rule evaluated to {} instead of bool",
+                x.type_name()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(rule: &str, value: Value) -> std::result::Result<bool, ExpressionError> {
+        Expression::parse(rule).unwrap().evaluate(&value, None)
+    }
+
+    fn check(rule: &str) -> bool {
+        eval(rule, json!({})).unwrap()
+    }
+
+    fn error_of(rule: &str) -> String {
+        eval(rule, json!({})).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn precedence() {
+        assert!(check("1 + 2 * 3 == 7"));
+        assert!(check("(1 + 2) * 3 == 9"));
+        assert!(check("10 - 4 - 3 == 3"));
+        assert!(check("-2 * 3 == -6"));
+        assert!(check("7 % 4 * 2 == 6"));
+        assert!(check("true || false && false"));
+        assert!(!check("(true || false) && false"));
+        assert!(check("!false && 1 < 2"));
+        assert!(check("1 + 1 in [2, 3]"));
+        assert!(check("false ? false : true ? true : false"));
+    }
+
+    #[test]
+    fn numbers_mix() {
+        assert!(check("1 == 1.0"));
+        assert!(check("3 / 2 == 1"));
+        assert!(check("3 / 2.0 == 1.5"));
+        assert!(check("int('42') + 1 == 43"));
+        assert!(check("string(1) + 'a' == '1a'"));
+    }
+
+    #[test]
+    fn overflow_and_division_errors() {
+        assert_eq!(
+            error_of("9223372036854775807 + 1 == 0"),
+            "integer overflow in +"
+        );
+        assert_eq!(
+            error_of("9223372036854775807 * 2 == 0"),
+            "integer overflow in *"
+        );
+        assert_eq!(
+            error_of("-(-9223372036854775807 - 1) == 0"),
+            "integer overflow in negation"
+        );
+        assert_eq!(error_of("1 / 0 == 0"), "division by zero");
+        assert_eq!(error_of("1 % 0 == 0"), "division by zero");
+        // floats follow ieee 754
+        assert!(check("1.0 / 0 > 1000000.0"));
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(error_of("1 < 'a'"), "can not compare int with string");
+        assert_eq!(error_of("1 + 1"), "rule evaluated to int instead of bool");
+        assert!(error_of("self.missing == 1").contains("no such key"));
+    }
+
+    #[test]
+    fn short_circuit_skips_errors() {
+        assert!(check("true || 1 / 0 == 0"));
+        assert!(!check("false && 1 / 0 == 0"));
+    }
+
+    #[test]
+    fn has_and_size() {
+        let spec = json!({"items": ["a", "b"], "name": "größe", "labels": {"a": "1"}});
+        assert!(eval("has(self.items)", spec.clone()).unwrap());
+        assert!(!eval("has(self.notes)", spec.clone()).unwrap());
+        assert!(eval("!has(self.notes) || self.notes.size() > 0", spec.clone()).unwrap());
+        assert!(eval("size(self.items) == 2", spec.clone()).unwrap());
+        assert!(eval("self.name.size() == 5", spec.clone()).unwrap());
+        assert!(eval("self.labels.size() == 1", spec.clone()).unwrap());
+        assert!(eval("size(1) == 1", spec.clone()).is_err());
+        assert!(
+            Expression::parse("has(self)")
+                .unwrap()
+                .evaluate(&spec, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn all_and_exists() {
+        let spec = json!({"slots": [{"nr": 1}, {"nr": 2}], "labels": {"a": "x", "b": "y"}});
+        assert!(eval("self.slots.all(s, s.nr > 0)", spec.clone()).unwrap());
+        assert!(!eval("self.slots.all(s, s.nr > 1)", spec.clone()).unwrap());
+        assert!(eval("self.slots.exists(s, s.nr == 2)", spec.clone()).unwrap());
+        assert!(!eval("self.slots.exists(s, s.nr == 3)", spec.clone()).unwrap());
+        // maps iterate their keys
+        assert!(eval("self.labels.all(k, k in ['a', 'b'])", spec.clone()).unwrap());
+        assert!(eval("[].all(x, false) && ![].exists(x, true)", spec.clone()).unwrap());
+        // the variable shadows self only within the predicate
+        assert!(eval("[1].all(self, self == 1) && has(self.slots)", spec.clone()).unwrap());
+        assert!(eval("self.slots.all(s, s)", spec).is_err());
+    }
+
+    #[test]
+    fn old_self_transition_rules() {
+        let rule = Expression::parse("self.nr >= oldSelf.nr").unwrap();
+        assert!(rule.is_transition_rule());
+        assert!(
+            !Expression::parse("self.nr >= 0")
+                .unwrap()
+                .is_transition_rule()
+        );
+        assert!(
+            rule.evaluate(&json!({"nr": 2}), Some(&json!({"nr": 1})))
+                .unwrap()
+        );
+        assert!(
+            !rule
+                .evaluate(&json!({"nr": 0}), Some(&json!({"nr": 1})))
+                .unwrap()
+        );
+        // without a stored value oldSelf is undeclared, the caller skips the rule
+        assert!(rule.evaluate(&json!({"nr": 2}), None).is_err());
+        let immutable = Expression::parse("self == oldSelf").unwrap();
+        assert!(immutable.evaluate(&json!("a"), Some(&json!("a"))).unwrap());
+        assert!(!immutable.evaluate(&json!("a"), Some(&json!("b"))).unwrap());
+    }
+
+    #[test]
+    fn strings_and_timestamps() {
+        assert!(check("'Kitchen'.lowerAscii().startsWith('kit')"));
+        assert!(check("'shelf-1'.endsWith('-1') && 'shelf'.contains('el')"));
+        assert!(check("timestamp('2026-10-18T12:30:00Z').month == 10"));
+        assert!(check("timestamp('2026-10-18T12:30:00Z') < now()"));
+        assert!(eval("timestamp('yesterday') < now()", json!({})).is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Expression::parse("1 +").is_err());
+        assert!(Expression::parse("(1 == 1").is_err());
+        assert!(Expression::parse("1 == 1 1").is_err());
+    }
+}
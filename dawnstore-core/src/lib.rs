@@ -1,6 +1,11 @@
+// the validation errors carry the offending values, results stay unboxed
+#![allow(clippy::result_large_err)]
+
 #[cfg(feature = "postgres")]
 pub mod backends;
 #[cfg(feature = "axum")]
 pub mod controllers;
 pub mod error;
+pub mod expression;
+pub mod merge;
 pub mod models;
@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use dawnstore_lib::ManagedFields;
+
+/// the part of an object which is patched and owned by field managers
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectDocument {
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    #[serde(default)]
+    pub spec: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldConflict {
+    pub path: String,
+    pub manager: String,
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// json pointers of all fields of the document, objects are descended into,
+/// arrays and scalars are owned as a whole
+pub fn field_paths(doc: &Value) -> Vec<String> {
+    fn collect(value: &Value, prefix: String, paths: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    collect(v, format!("{prefix}/{}", escape(k)), paths);
+                }
+            }
+            _ if prefix.is_empty() => {}
+            _ => paths.push(prefix),
+        }
+    }
+    let mut paths = Vec::new();
+    collect(doc, String::new(), &mut paths);
+    paths
+}
+
+/// one of the paths is the other or lies below it
+fn overlaps(a: &str, b: &str) -> bool {
+    let below =
+        |x: &str, y: &str| x.len() > y.len() && x.starts_with(y) && x[y.len()..].starts_with('/');
+    a == b || below(a, b) || below(b, a)
+}
+
+/// sets the value at the path and creates missing objects on the way
+fn set_path(doc: &mut Value, path: &str, value: Value) {
+    let mut segments = path.split('/').skip(1).map(unescape).collect::<Vec<_>>();
+    let Some(last) = segments.pop() else {
+        return;
+    };
+    let mut position = doc;
+    for seg in segments {
+        if !position.is_object() {
+            *position = Value::Object(Map::new());
+        }
+        position = position
+            .as_object_mut()
+            .expect("just made sure this is an object")
+            .entry(seg)
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    if !position.is_object() {
+        *position = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = position {
+        map.insert(last, value);
+    }
+}
+
+fn remove_path(doc: &mut Value, path: &str) {
+    let Some((parent, last)) = path.rsplit_once('/') else {
+        return;
+    };
+    if let Some(Value::Object(map)) = doc.pointer_mut(parent) {
+        map.remove(&unescape(last));
+    }
+}
+
+/// merges the manifest of a manager into the stored document
+///
+/// the manager owns exactly the fields of its manifest afterwards, fields it
+/// owned before and left out are removed unless another manager owns them as
+/// well. changing a field of another manager is a conflict unless forced,
+/// in which case the other manager loses the field. applying the same value
+/// as another manager shares the ownership.
+pub fn server_side_apply(
+    stored: &Value,
+    managed: &ManagedFields,
+    manifest: &Value,
+    manager: &str,
+    force: bool,
+) -> Result<(Value, ManagedFields), Vec<FieldConflict>> {
+    let fields = field_paths(manifest);
+    let mut managed = managed.clone();
+
+    let mut conflicts = Vec::new();
+    for (other, owned) in managed.iter_mut().filter(|(m, _)| m.as_str() != manager) {
+        let mut taken = Vec::new();
+        for path in &fields {
+            if stored.pointer(path) == manifest.pointer(path) {
+                continue;
+            }
+            for o in owned.iter().filter(|o| overlaps(o, path)) {
+                if !taken.contains(o) {
+                    conflicts.push(FieldConflict {
+                        path: o.clone(),
+                        manager: other.clone(),
+                    });
+                    taken.push(o.clone());
+                }
+            }
+        }
+        if force {
+            owned.retain(|o| !taken.contains(o));
+        }
+    }
+    if !force && !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+
+    let mut merged = stored.clone();
+    let previous = managed.remove(manager).unwrap_or_default();
+    for path in previous.iter().filter(|x| !fields.contains(x)) {
+        let shared = managed
+            .values()
+            .any(|owned| owned.iter().any(|o| overlaps(o, path)));
+        if !shared {
+            remove_path(&mut merged, path);
+        }
+    }
+    for path in &fields {
+        if let Some(value) = manifest.pointer(path) {
+            set_path(&mut merged, path, value.clone());
+        }
+    }
+
+    managed.insert(manager.to_owned(), fields);
+    managed.retain(|_, owned| !owned.is_empty());
+    Ok((merged, managed))
+}
+
+/// bookkeeping of a write which replaces the whole document, the manager
+/// takes over every changed field and all managers lose removed fields
+pub fn track_update(
+    stored: Option<&Value>,
+    managed: &ManagedFields,
+    new: &Value,
+    manager: &str,
+) -> ManagedFields {
+    let unchanged = |path: &str| {
+        let value = new.pointer(path);
+        value.is_some() && value == stored.and_then(|x| x.pointer(path))
+    };
+    let mut managed = managed.clone();
+    for (other, owned) in managed.iter_mut() {
+        if other == manager {
+            owned.retain(|o| new.pointer(o).is_some());
+        } else {
+            owned.retain(|o| unchanged(o));
+        }
+    }
+    let changed = field_paths(new)
+        .into_iter()
+        .filter(|p| !unchanged(p))
+        .collect::<Vec<_>>();
+    let owned = managed.entry(manager.to_owned()).or_default();
+    for path in changed {
+        if !owned.contains(&path) {
+            owned.push(path);
+        }
+    }
+    managed.retain(|_, owned| !owned.is_empty());
+    managed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn managed(entries: &[(&str, &[&str])]) -> ManagedFields {
+        entries
+            .iter()
+            .map(|(m, paths)| (m.to_string(), paths.iter().map(|x| x.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn field_paths_descend_into_objects_only() {
+        let doc = json!({"labels": {"a/b": "x"}, "spec": {"list": [1, 2], "n": {"m": null}}});
+        let mut paths = field_paths(&doc);
+        paths.sort();
+        assert_eq!(paths, ["/labels/a~1b", "/spec/list", "/spec/n/m"]);
+        assert!(field_paths(&json!(1)).is_empty());
+    }
+
+    #[test]
+    fn overlapping_paths() {
+        assert!(overlaps("/spec/a", "/spec/a"));
+        assert!(overlaps("/spec/a", "/spec/a/b"));
+        assert!(overlaps("/spec/a/b", "/spec/a"));
+        assert!(!overlaps("/spec/a", "/spec/ab"));
+        assert!(!overlaps("/spec/a/b", "/spec/a/c"));
+    }
+
+    #[test]
+    fn first_apply_owns_its_fields() {
+        let manifest = json!({"spec": {"a": 1, "b": {"c": 2}}});
+        let (merged, owners) =
+            server_side_apply(&json!({}), &ManagedFields::new(), &manifest, "m1", false).unwrap();
+        assert_eq!(merged, manifest);
+        assert_eq!(owners, managed(&[("m1", &["/spec/a", "/spec/b/c"])]));
+    }
+
+    #[test]
+    fn changing_a_field_of_another_manager_conflicts() {
+        let stored = json!({"spec": {"a": 1, "b": 2}});
+        let owners = managed(&[("m1", &["/spec/a", "/spec/b"])]);
+        let conflicts = server_side_apply(
+            &stored,
+            &owners,
+            &json!({"spec": {"a": 3, "b": 2}}),
+            "m2",
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            conflicts,
+            [FieldConflict {
+                path: "/spec/a".to_string(),
+                manager: "m1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn overlapping_fields_conflict() {
+        // the other manager owns a field below the one which is replaced
+        let stored = json!({"spec": {"a": {"b": 1}}});
+        let owners = managed(&[("m1", &["/spec/a/b"])]);
+        let conflicts =
+            server_side_apply(&stored, &owners, &json!({"spec": {"a": 5}}), "m2", false)
+                .unwrap_err();
+        assert_eq!(conflicts[0].path, "/spec/a/b");
+
+        // and a field above the one which is set, arrays are owned as a whole
+        let stored = json!({"spec": {"a": [1]}});
+        let owners = managed(&[("m1", &["/spec/a"])]);
+        let conflicts = server_side_apply(
+            &stored,
+            &owners,
+            &json!({"spec": {"a": {"x": 1}}}),
+            "m2",
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "/spec/a");
+    }
+
+    #[test]
+    fn equal_values_share_ownership() {
+        let stored = json!({"spec": {"a": 1, "b": 2}});
+        let owners = managed(&[("m1", &["/spec/a", "/spec/b"])]);
+        let (merged, owners) =
+            server_side_apply(&stored, &owners, &json!({"spec": {"a": 1}}), "m2", false).unwrap();
+        assert_eq!(merged, stored);
+        assert_eq!(
+            owners,
+            managed(&[("m1", &["/spec/a", "/spec/b"]), ("m2", &["/spec/a"])])
+        );
+
+        // a shared field stays when one of its managers leaves it out
+        let (merged, owners) =
+            server_side_apply(&merged, &owners, &json!({"spec": {"b": 2}}), "m1", false).unwrap();
+        assert_eq!(merged, stored);
+        assert_eq!(
+            owners,
+            managed(&[("m1", &["/spec/b"]), ("m2", &["/spec/a"])])
+        );
+    }
+
+    #[test]
+    fn forced_apply_takes_over_fields() {
+        let stored = json!({"spec": {"a": 1, "b": 2}});
+        let owners = managed(&[("m1", &["/spec/a"]), ("m3", &["/spec/b"])]);
+        let (merged, owners) =
+            server_side_apply(&stored, &owners, &json!({"spec": {"a": 3}}), "m2", true).unwrap();
+        assert_eq!(merged, json!({"spec": {"a": 3, "b": 2}}));
+        // managers without any fields left are dropped
+        assert_eq!(
+            owners,
+            managed(&[("m2", &["/spec/a"]), ("m3", &["/spec/b"])])
+        );
+    }
+
+    #[test]
+    fn dropped_fields_are_removed() {
+        let stored = json!({"labels": {"x": "1"}, "spec": {"a": 1, "b": {"c": 2}}});
+        let owners = managed(&[("m1", &["/labels/x", "/spec/a", "/spec/b/c"])]);
+        let (merged, owners) =
+            server_side_apply(&stored, &owners, &json!({"spec": {"a": 1}}), "m1", false).unwrap();
+        assert_eq!(merged, json!({"labels": {}, "spec": {"a": 1, "b": {}}}));
+        assert_eq!(owners, managed(&[("m1", &["/spec/a"])]));
+
+        // fields without a manager are left alone
+        let stored = json!({"spec": {"a": 1, "unmanaged": true}});
+        let owners = managed(&[("m1", &["/spec/a"])]);
+        let (merged, _) =
+            server_side_apply(&stored, &owners, &json!({"spec": {}}), "m1", false).unwrap();
+        assert_eq!(merged, json!({"spec": {"unmanaged": true}}));
+    }
+
+    #[test]
+    fn update_takes_changed_fields() {
+        let stored = json!({"spec": {"a": 1, "b": 2, "c": 3}});
+        let owners = managed(&[("m1", &["/spec/a", "/spec/b"]), ("m2", &["/spec/c"])]);
+        let new = json!({"spec": {"a": 1, "b": 5, "d": 4}});
+        let owners = track_update(Some(&stored), &owners, &new, "m2");
+        // m1 keeps the unchanged field, m2 loses the removed one
+        assert_eq!(
+            owners,
+            managed(&[("m1", &["/spec/a"]), ("m2", &["/spec/b", "/spec/d"])])
+        );
+
+        let owners = track_update(None, &ManagedFields::new(), &new, "m1");
+        assert_eq!(
+            owners,
+            managed(&[("m1", &["/spec/a", "/spec/b", "/spec/d"])])
+        );
+    }
+}
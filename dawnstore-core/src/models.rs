@@ -1,12 +1,24 @@
+use std::time::Duration;
+
 pub use dawnstore_lib::*;
 
 pub struct ForeignKey {
+    /// dot separated spec path, `[*]` after a field references from every
+    /// element of its array, e.g. `slots[*].item`
     pub path: String,
     pub parent_path: Option<String>,
     pub ty: ForeignKeyType,
     pub behaviour: ForeignKeyBehaviour,
-    /// None: different kinds are allowed
-    pub foreign_kind: Option<String>,
+    /// empty: different kinds are allowed
+    pub allowed_kinds: Vec<ForeignKind>,
+    /// objects can not reference themselves over the key, also not through
+    /// other objects
+    pub acyclic: bool,
+    /// None: the references are strings. with a schema a reference can also
+    /// be `{"ref": "...", ...properties}`, the properties are stored on the
+    /// relation
+    pub edge_schema: Option<schemars::Schema>,
+    pub namespace_policy: NamespacePolicy,
 }
 impl ForeignKey {
     pub fn new(
@@ -19,10 +31,159 @@ impl ForeignKey {
             path: path.into(),
             ty,
             behaviour: ForeignKeyBehaviour::Fill,
-            foreign_kind: foreign_kind.map(|x| x.into()),
+            allowed_kinds: foreign_kind
+                .map(|x| ForeignKind::new(x))
+                .into_iter()
+                .collect(),
             parent_path: parent_path.map(|x| x.into()),
+            acyclic: false,
+            edge_schema: None,
+            namespace_policy: NamespacePolicy::Any,
+        }
+    }
+
+    pub fn acyclic(mut self) -> Self {
+        self.acyclic = true;
+        self
+    }
+
+    pub fn with_namespace_policy(mut self, policy: NamespacePolicy) -> Self {
+        self.namespace_policy = policy;
+        self
+    }
+
+    pub fn with_edge_schema<P: schemars::JsonSchema>(mut self) -> Self {
+        self.edge_schema = Some(schemars::schema_for!(P));
+        self
+    }
+
+    /// the key can reference objects of any of these kinds
+    pub fn with_allowed_kinds(mut self, kinds: impl IntoIterator<Item = ForeignKind>) -> Self {
+        self.allowed_kinds = kinds.into_iter().collect();
+        self
+    }
+}
+
+/// optional settings of a kind, used when seeding its object schema
+#[derive(Default)]
+pub struct KindOptions {
+    /// None: the kind has no status subresource
+    pub status_schema: Option<schemars::Schema>,
+    /// objects without expires_at expire this long after their creation, or
+    /// after the first apply with the ttl if they existed before
+    pub ttl: Option<Duration>,
+    /// the spec of the objects can not change after creation, single fields
+    /// are marked with the `x-dawnstore-immutable` schema extension
+    pub immutable: bool,
+    pub validation_rules: Vec<ValidationRule>,
+    pub unique_constraints: Vec<UniqueConstraint>,
+    pub indexes: Vec<SpecIndex>,
+}
+impl KindOptions {
+    pub fn with_status<S: schemars::JsonSchema>(mut self) -> Self {
+        self.status_schema = Some(schemars::schema_for!(S));
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    pub fn with_rule(mut self, rule: ValidationRule) -> Self {
+        self.validation_rules.push(rule);
+        self
+    }
+
+    pub fn with_unique_constraint(mut self, constraint: UniqueConstraint) -> Self {
+        self.unique_constraints.push(constraint);
+        self
+    }
+
+    pub fn with_index(mut self, index: SpecIndex) -> Self {
+        self.indexes.push(index);
+        self
+    }
+}
+
+#[derive(Debug, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "spec_index_type", rename_all = "PascalCase")]
+pub enum SpecIndexType {
+    /// equality and ordering of scalar values
+    BTree,
+    /// containment of values in arrays
+    Gin,
+}
+
+/// an expression index over a spec path, used by the field selectors
+pub struct SpecIndex {
+    /// dot separated spec path like the foreign key paths
+    pub path: String,
+    pub ty: SpecIndexType,
+}
+impl SpecIndex {
+    pub fn btree(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ty: SpecIndexType::BTree,
+        }
+    }
+
+    pub fn gin(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ty: SpecIndexType::Gin,
+        }
+    }
+}
+
+#[derive(Debug, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "unique_constraint_scope", rename_all = "PascalCase")]
+pub enum UniqueScope {
+    Global,
+    Namespace,
+    /// unique among the objects referencing the same object through a foreign key
+    ForeignKey,
+}
+
+/// the combination of the values at the spec paths is unique within the scope,
+/// objects missing one of the values are not constrained
+pub struct UniqueConstraint {
+    pub name: String,
+    /// dot separated spec paths like the foreign key paths
+    pub paths: Vec<String>,
+    pub scope: UniqueScope,
+    pub foreign_key_path: Option<String>,
+}
+impl UniqueConstraint {
+    pub fn new(
+        name: impl Into<String>,
+        paths: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            paths: paths.into_iter().map(|x| x.into()).collect(),
+            scope: UniqueScope::Global,
+            foreign_key_path: None,
         }
     }
+
+    pub fn per_namespace(mut self) -> Self {
+        self.scope = UniqueScope::Namespace;
+        self.foreign_key_path = None;
+        self
+    }
+
+    pub fn per_foreign_key(mut self, key_path: impl Into<String>) -> Self {
+        self.scope = UniqueScope::ForeignKey;
+        self.foreign_key_path = Some(key_path.into());
+        self
+    }
 }
 
 #[derive(Debug, sqlx::Type, Clone, PartialEq, Eq)]
@@ -70,10 +231,43 @@ pub struct TestCar {
 pub struct Container {
     pub nr: u32,
     pub notes: Option<String>,
+    #[schemars(extend("x-dawnstore-immutable" = true))]
+    pub serial: Option<String>,
     pub parent: Option<String>,
     // #[schemars(skip)]
     pub parent_object: Option<ReturnObject<Box<Container>>>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub items: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub slots: Vec<ContainerSlot>,
+}
+
+#[derive(schemars::JsonSchema, serde::Serialize, serde::Deserialize)]
+#[schemars(deny_unknown_fields)]
+pub struct ContainerSlot {
+    pub label: String,
+    pub item: Option<SlotItem>,
+    pub item_object: Option<ReturnObject<Box<Container>>>,
+    pub item_properties: Option<SlotPlacement>,
+}
+
+/// the item of a slot, optionally with its placement
+#[derive(schemars::JsonSchema, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum SlotItem {
+    Name(String),
+    Placed {
+        r#ref: String,
+        #[serde(flatten)]
+        placement: SlotPlacement,
+    },
+}
+
+/// properties of the relation between a container and a slot item
+#[derive(schemars::JsonSchema, serde::Serialize, serde::Deserialize)]
+pub struct SlotPlacement {
+    pub position: Option<u32>,
+    pub quantity: Option<u32>,
 }
@@ -4,7 +4,9 @@ use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use uuid::Uuid;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, JsonSchema)]
+pub mod query;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, JsonSchema)]
 pub struct ObjectOwner {
     pub api_version: String,
     pub kind: String,
@@ -12,6 +14,31 @@ pub struct ObjectOwner {
     pub id: uuid::Uuid,
 }
 
+/// field manager name -> json pointers into `{"labels": .., "annotations": .., "spec": ..}`
+/// which were last set by that manager
+pub type ManagedFields = BTreeMap<String, Vec<String>>;
+
+/// the fields of [`Object`] next to the flattened spec, a spec property with
+/// one of these names would be taken by the object, so kinds can not use them
+pub const RESERVED_SPEC_FIELDS: &[&str] = &[
+    "id",
+    "created_at",
+    "updated_at",
+    "annotations",
+    "labels",
+    "finalizers",
+    "owners",
+    "expires_at",
+    "deletion_timestamp",
+    "managed_fields",
+    "resource_version",
+    "namespace",
+    "api_version",
+    "kind",
+    "name",
+    "status",
+];
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct Object<T> {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,6 +51,30 @@ pub struct Object<T> {
     pub annotations: Option<BTreeMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub labels: Option<BTreeMap<String, String>>,
+    /// None: keeps the stored finalizers, an empty list removes them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalizers: Option<Vec<String>>,
+    /// the object gets garbage collected once all owners are deleted,
+    /// None keeps the stored owners
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owners: Option<Vec<ObjectOwner>>,
+    /// None (left out): keeps the stored expiry, Some(None) (null): removes
+    /// it. objects without an expiry fall back to the ttl of the kind
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires_at: Option<Option<DateTime<Utc>>>,
+    /// ignored by apply, set when an object with finalizers gets deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_timestamp: Option<DateTime<Utc>>,
+    /// ignored by apply, maintained by the store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub managed_fields: Option<ManagedFields>,
+    /// ignored by apply, use the preconditions of a transaction instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_version: Option<i64>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub namespace: Option<String>,
@@ -32,6 +83,9 @@ pub struct Object<T> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub kind: Option<String>,
     pub name: String,
+    /// ignored by apply, only written through the status endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<serde_json::Value>,
     #[serde(flatten)]
     pub spec: T,
 }
@@ -48,11 +102,26 @@ pub struct ReturnObject<T> {
     pub annotations: Option<BTreeMap<String, String>>,
     #[serde(skip_serializing_if = "is_none_or_empty")]
     pub labels: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub finalizers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<ObjectOwner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_timestamp: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub managed_fields: ManagedFields,
+    /// increased on every write of the object
+    #[serde(default)]
+    pub resource_version: i64,
 
     pub namespace: String,
     pub api_version: String,
     pub kind: String,
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<serde_json::Value>,
 
     #[serde(flatten)]
     pub spec: T,
@@ -62,6 +131,15 @@ fn is_none_or_empty(v: &Option<BTreeMap<String, String>>) -> bool {
     v.as_ref().is_none_or(|map| map.is_empty())
 }
 
+/// tells a null value, Some(None), apart from a left out one, None
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
 pub struct GetObjectsFilter {
     pub namespace: Option<String>,
@@ -70,10 +148,57 @@ pub struct GetObjectsFilter {
     pub fill_child_foreign_keys: bool,
     pub fill_parent_foreign_keys: bool,
     pub ids: Option<Vec<Uuid>>,
+    /// all selectors have to match, uses the declared spec indexes of the kind
+    pub field_selectors: Option<Vec<FieldSelector>>,
+    /// see [query::parse_query], combined with the other filters
+    pub query: Option<String>,
     pub page: Option<usize>,
     pub page_size: Option<usize>,
 }
 
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSelectorOperator {
+    /// compares the text form of the value at the path
+    Equals,
+    NotEquals,
+    /// the array at the path contains the value
+    Contains,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct FieldSelector {
+    /// dot separated spec path
+    pub path: String,
+    pub operator: FieldSelectorOperator,
+    pub value: String,
+}
+
+/// parses a field selector of the form `path=value,path!=value,path@>value`
+pub fn parse_field_selector(selector: &str) -> Option<Vec<FieldSelector>> {
+    selector
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let (path, operator, value) = if let Some((p, v)) = x.split_once("!=") {
+                (p, FieldSelectorOperator::NotEquals, v)
+            } else if let Some((p, v)) = x.split_once("@>") {
+                (p, FieldSelectorOperator::Contains, v)
+            } else if let Some((p, v)) = x.split_once("==") {
+                (p, FieldSelectorOperator::Equals, v)
+            } else {
+                let (p, v) = x.split_once('=')?;
+                (p, FieldSelectorOperator::Equals, v)
+            };
+            Some(FieldSelector {
+                path: path.trim().to_string(),
+                operator,
+                value: value.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct ListOfObjects {
     /// should always be list
@@ -83,11 +208,144 @@ pub struct ListOfObjects {
     pub list: Vec<ObjectAny>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+/// finalizer which keeps an owner alive until its dependents are deleted
+pub const FOREGROUND_DELETION_FINALIZER: &str = "foregroundDeletion";
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PropagationPolicy {
+    /// the owner is deleted right away, dependents are collected afterwards
+    #[default]
+    Background,
+    /// the owner stays until all dependents are deleted
+    Foreground,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct DeleteObject {
     pub namespace: Option<String>,
     pub kind: String,
     pub name: String,
+    pub propagation_policy: Option<PropagationPolicy>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct RemoveFinalizer {
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub name: String,
+    pub finalizer: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct UpdateObjectStatus {
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub name: String,
+    pub status: serde_json::Value,
+}
+
+/// field manager of applies which do not name one
+pub const DEFAULT_APPLY_FIELD_MANAGER: &str = "apply";
+/// field manager of patches
+pub const PATCH_FIELD_MANAGER: &str = "patch";
+
+/// query parameters of the apply endpoint
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct ApplyOptions {
+    /// merge the manifests into the stored objects instead of replacing them,
+    /// requires a field manager
+    #[serde(default)]
+    pub server_side: bool,
+    pub field_manager: Option<String>,
+    /// take over fields owned by other managers instead of failing
+    #[serde(default)]
+    pub force_conflicts: bool,
+    /// delete the objects matched by selector and applied_set which are not
+    /// part of the payload
+    #[serde(default)]
+    pub prune: bool,
+    /// label selector of the form `key=value,other=value`
+    pub selector: Option<String>,
+    /// labels every applied object with APPLIED_SET_LABEL
+    pub applied_set: Option<String>,
+}
+
+/// label which marks the objects applied as part of an applied set
+pub const APPLIED_SET_LABEL: &str = "dawnstore.io/applied-set";
+
+/// parses a label selector of the form `key=value,other=value`
+pub fn parse_label_selector(selector: &str) -> Option<BTreeMap<String, String>> {
+    selector
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            x.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        })
+        .collect()
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PatchType {
+    /// RFC 7386 merge patch
+    #[default]
+    Merge,
+    /// RFC 6902 json patch, test operations act as preconditions
+    Json,
+}
+
+/// the patch is applied to the document `{"labels": .., "annotations": .., "spec": ..}`
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct PatchObject {
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub name: String,
+    pub patch_type: PatchType,
+    pub patch: serde_json::Value,
+}
+
+/// checked against the object an operation targets, before the operation runs
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct Precondition {
+    /// Some(false): the object must not exist yet
+    pub exists: Option<bool>,
+    /// the object must exist with exactly this resource version
+    pub resource_version: Option<i64>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransactionOperation {
+    /// the precondition is checked for every object of the payload
+    Apply {
+        object: serde_json::Value,
+        #[serde(default)]
+        options: ApplyOptions,
+        precondition: Option<Precondition>,
+    },
+    Patch {
+        patch: PatchObject,
+        precondition: Option<Precondition>,
+    },
+    Delete {
+        delete: DeleteObject,
+        precondition: Option<Precondition>,
+    },
+}
+
+/// operations which are executed in order and all or nothing
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct Transaction {
+    pub operations: Vec<TransactionOperation>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransactionResult {
+    Apply { objects: Vec<ReturnAny> },
+    Patch { object: Box<ReturnAny> },
+    Delete,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -96,8 +354,133 @@ pub struct ResourceDefinition {
     pub kind: String,
     pub aliases: Vec<String>,
     pub json_schema: String,
+    /// None: the kind has no status subresource
+    pub status_json_schema: Option<String>,
+    /// None: objects of the kind only expire with an explicit expires_at
+    pub ttl_seconds: Option<i64>,
+    /// the spec of the objects can not change after creation
+    #[serde(default)]
+    pub immutable: bool,
+    #[serde(default)]
+    pub validation_rules: Vec<ValidationRule>,
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKeyDefinition>,
 }
 
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct ForeignKeyDefinition {
+    pub path: String,
+    pub parent_path: Option<String>,
+    /// One, OneOptional, OneOrMany or NoneOrMany
+    pub r#type: String,
+    /// empty: every kind can be referenced
+    pub allowed_kinds: Vec<ForeignKind>,
+    #[serde(default)]
+    pub acyclic: bool,
+    /// None: the references carry no properties
+    #[serde(default)]
+    pub edge_json_schema: Option<String>,
+    #[serde(default)]
+    pub namespace_policy: NamespacePolicy,
+}
+
+/// the namespaces a foreign key can reference objects in
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub enum NamespacePolicy {
+    /// only the namespace of the referencing object
+    SameNamespace,
+    /// the namespace of the referencing object and these
+    AllowList(Vec<String>),
+    #[default]
+    Any,
+}
+impl NamespacePolicy {
+    pub fn allows(&self, namespace: &str, foreign_namespace: &str) -> bool {
+        match self {
+            Self::SameNamespace => namespace == foreign_namespace,
+            Self::AllowList(x) => {
+                namespace == foreign_namespace || x.iter().any(|x| x == foreign_namespace)
+            }
+            Self::Any => true,
+        }
+    }
+}
+impl std::fmt::Display for NamespacePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SameNamespace => write!(f, "same namespace only"),
+            Self::AllowList(x) => write!(f, "same namespace or {} only", x.join(", ")),
+            Self::Any => write!(f, "any namespace"),
+        }
+    }
+}
+
+/// a kind a foreign key can reference, optionally only in one api version
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKind {
+    pub kind: String,
+    /// None: every api version of the kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+}
+impl ForeignKind {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            api_version: None,
+        }
+    }
+
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = Some(api_version.into());
+        self
+    }
+}
+impl std::fmt::Display for ForeignKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.api_version {
+            Some(api_version) => write!(f, "{api_version}/{}", self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// a cel like rule over the spec, `self` is the value at the path and
+/// `oldSelf` the stored value. rules using `oldSelf` are transition rules
+/// and only checked when an object is updated.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct ValidationRule {
+    pub rule: String,
+    /// dot separated spec path, None: the whole spec.
+    /// the rule is skipped while the path is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+impl ValidationRule {
+    pub fn new(rule: impl Into<String>) -> Self {
+        Self {
+            rule: rule.into(),
+            path: None,
+            message: None,
+        }
+    }
+
+    pub fn at(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+/// schema extension marking a spec field which can not change after creation
+pub const IMMUTABLE_SCHEMA_EXTENSION: &str = "x-dawnstore-immutable";
+
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct GetResourceDefinitionFilter {}
 
@@ -124,3 +507,217 @@ pub struct GetObjectInfosFilter {
     pub page: Option<usize>,
     pub page_size: Option<usize>,
 }
+
+/// full-text search over names, labels, annotations and the strings of the spec
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct SearchFilter {
+    /// web search syntax, e.g. `reis -basmati` or `"old screws" or nails`
+    pub query: String,
+    pub namespace: Option<String>,
+    /// the built-in lease and event objects are only found when named here
+    pub kind: Option<String>,
+    /// at most 250, 50 if not set
+    pub limit: Option<usize>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct SearchHit {
+    pub namespace: String,
+    pub id: Uuid,
+    pub api_version: String,
+    pub kind: String,
+    pub name: String,
+    pub rank: f32,
+    /// the text around the matches, which are enclosed in `<b></b>`
+    pub headline: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct SearchResults {
+    /// best match first
+    pub hits: Vec<SearchHit>,
+}
+
+pub const LEASE_API_VERSION: &str = "v1";
+pub const LEASE_KIND: &str = "lease";
+pub const MAX_LEASE_DURATION_SECONDS: i64 = 60 * 60;
+
+/// spec of the built-in lease kind, only changed through the lease endpoints
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Lease {
+    pub holder_identity: Option<String>,
+    pub lease_duration_seconds: i64,
+    pub acquire_time: Option<DateTime<Utc>>,
+    pub renew_time: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct LeaseRequest {
+    pub namespace: Option<String>,
+    pub name: String,
+    pub holder_identity: String,
+    /// 1 to MAX_LEASE_DURATION_SECONDS, None: 15 seconds on acquire and the
+    /// stored duration on renew
+    pub lease_duration_seconds: Option<i64>,
+}
+
+pub const EVENT_API_VERSION: &str = "v1";
+pub const EVENT_KIND: &str = "event";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ObjectReference {
+    pub namespace: String,
+    pub api_version: Option<String>,
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum EventType {
+    Normal,
+    Warning,
+}
+
+/// spec of the built-in event kind, repeated events only bump count and last_seen
+/// and keep the latest message
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Event {
+    pub involved_object: ObjectReference,
+    pub event_type: EventType,
+    pub reason: String,
+    pub message: String,
+    pub count: i64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct RecordEvent {
+    pub involved_object: ObjectReference,
+    pub event_type: EventType,
+    pub reason: String,
+    pub message: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+pub struct GetEventsFilter {
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub name: String,
+    pub limit: Option<usize>,
+}
+
+/// deepest level a traversal follows, also the default depth
+pub const MAX_TRAVERSE_DEPTH: u32 = 64;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraverseDirection {
+    /// the objects referenced by the start object, e.g. its parents
+    #[default]
+    Ancestors,
+    /// the objects referencing the start object, e.g. its children
+    Descendants,
+}
+
+/// a walk over the relations starting at one object
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+pub struct TraverseFilter {
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub name: String,
+    #[serde(default)]
+    pub direction: TraverseDirection,
+    /// key path of the followed foreign key, e.g. parent. None: every foreign key
+    pub foreign_key: Option<String>,
+    /// None: up to MAX_TRAVERSE_DEPTH levels
+    pub depth: Option<u32>,
+}
+
+/// `object` references `foreign_object` through `foreign_key`
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct RelationEdge {
+    pub object: String,
+    pub foreign_object: String,
+    pub foreign_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<serde_json::Value>,
+    /// levels away from the start object, 1 for its own relations
+    pub depth: u32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct TraverseResult {
+    /// the start object first, then the reached objects by depth
+    pub objects: Vec<ReturnAny>,
+    pub edges: Vec<RelationEdge>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+pub struct FsckOptions {
+    /// fix the issues which can be fixed without touching the specs
+    #[serde(default)]
+    pub repair: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsckIssueKind {
+    /// a relation of a deleted object or foreign key
+    DanglingRelation,
+    /// a relation whose reference is no longer in the spec
+    StaleRelation,
+    /// a reference in the spec without its relation
+    MissingRelation,
+    /// the relation has other properties than the reference in the spec
+    RelationPropertiesMismatch,
+    /// a reference in the spec to an object which does not exist
+    DanglingReference,
+    /// the object fails the current schema, rules or foreign keys of its kind
+    SchemaViolation,
+    /// the string id is not namespace/kind/name
+    StringIdMismatch,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct FsckIssue {
+    pub kind: FsckIssueKind,
+    /// None: the object of the issue does not exist anymore
+    pub object: Option<String>,
+    pub message: String,
+    pub repaired: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct FsckReport {
+    pub scanned_objects: usize,
+    pub issues: Vec<FsckIssue>,
+}
+
+/// body of every error response of the api
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct ErrorResponse {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub errors: Vec<ErrorDetail>,
+}
+
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct ErrorDetail {
+    /// `{namespace}/{kind}/{name}` of the object the error belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    /// json pointer into the object as it was sent, the spec fields are at the root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+    pub message: String,
+    pub code: String,
+}
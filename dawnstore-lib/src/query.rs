@@ -0,0 +1,578 @@
+//! query language of the get endpoint, e.g.
+//! `kind = container and spec.nr > 2 and labels.room = "kitchen" and ref(parent).name = "shelf1"`
+//!
+//! ```text
+//! query      := or
+//! or         := and ("or" and)*
+//! and        := unary ("and" unary)*
+//! unary      := "not" unary | "(" query ")" | comparison
+//! comparison := field op value | field "in" "(" value ("," value)* ")"
+//! op         := "=" | "!=" | "<" | "<=" | ">" | ">=" | "contains"
+//! field      := "namespace" | "kind" | "name" | "api_version"
+//!             | "labels." key | "annotations." key | "spec." segment ("." segment)*
+//!             | "ref(" key_path ")." field
+//! value      := string | number | "true" | "false" | "null" | word
+//! ```
+//!
+//! keywords are case insensitive, keys and words may be quoted with `"`
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Namespace,
+    Kind,
+    Name,
+    ApiVersion,
+    Label(String),
+    Annotation(String),
+    /// plain spec path, the segments consist of `[A-Za-z0-9_-]`
+    Spec(Vec<String>),
+    /// field of the object referenced by the foreign key with this key path
+    Ref {
+        key_path: String,
+        field: Box<Field>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equals,
+    NotEquals,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// the array contains the value, a text contains the string
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// all terms of a chain, so long chains do not nest
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Compare {
+        field: Field,
+        operator: Operator,
+        value: Value,
+    },
+    In {
+        field: Field,
+        values: Vec<Value>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// byte offset within the query
+    pub position: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Number(serde_json::Number),
+    Symbol(&'static str),
+    /// the raw key path of `ref(...)`
+    Ref(String),
+}
+
+/// brackets, `not` and `ref` nested deeper are rejected, the parser and the
+/// compiled query recurse on them
+pub const MAX_QUERY_DEPTH: usize = 32;
+
+const SYMBOLS: [&str; 10] = ["!=", "<=", ">=", "=", "<", ">", "(", ")", ",", "."];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let error = |position: usize, message: &str| QueryError {
+        position,
+        message: message.to_owned(),
+    };
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < query.len() {
+        let rest = &query[pos..];
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+        let start = pos;
+        if c == '"' {
+            let mut text = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            loop {
+                match chars.next() {
+                    Some((i, '"')) => {
+                        pos += i + 1;
+                        break;
+                    }
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, x)) => text.push(x),
+                        None => return Err(error(start, "unterminated string")),
+                    },
+                    Some((_, x)) => text.push(x),
+                    None => return Err(error(start, "unterminated string")),
+                }
+            }
+            tokens.push((start, Token::Quoted(text)));
+        } else if c.is_ascii_digit()
+            || (c == '-' && rest[1..].starts_with(|x: char| x.is_ascii_digit()))
+        {
+            let bytes = rest.as_bytes();
+            let digits = |from: usize| {
+                from + bytes[from..]
+                    .iter()
+                    .take_while(|x| x.is_ascii_digit())
+                    .count()
+            };
+            let mut len = digits(1);
+            // after a dot the number is an array index of a spec path, e.g. `spec.a.0.1`
+            let index = matches!(tokens.last(), Some((_, Token::Symbol("."))));
+            // a dot without digits separates the fields of a spec path
+            if !index
+                && bytes.get(len) == Some(&b'.')
+                && bytes.get(len + 1).is_some_and(u8::is_ascii_digit)
+            {
+                len = digits(len + 1);
+            }
+            if !index && matches!(bytes.get(len), Some(b'e' | b'E')) {
+                let sign = matches!(bytes.get(len + 1), Some(b'+' | b'-')) as usize;
+                len = digits(len + 1 + sign);
+            }
+            let number = serde_json::from_str::<serde_json::Number>(&rest[..len])
+                .map_err(|_| error(start, "invalid number"))?;
+            tokens.push((start, Token::Number(number)));
+            pos += len;
+        } else if is_word_char(c) {
+            let len = rest.find(|x| !is_word_char(x)).unwrap_or(rest.len());
+            let word = &rest[..len];
+            pos += len;
+            // key paths contain brackets and dots, they are taken as they are
+            if word.eq_ignore_ascii_case("ref") && query[pos..].starts_with('(') {
+                let Some(end) = query[pos..].find(')') else {
+                    return Err(error(start, "unterminated ref"));
+                };
+                let key_path = query[pos + 1..pos + end].trim();
+                if key_path.is_empty() {
+                    return Err(error(start, "ref without key path"));
+                }
+                tokens.push((start, Token::Ref(key_path.to_owned())));
+                pos += end + 1;
+            } else {
+                tokens.push((start, Token::Word(word.to_owned())));
+            }
+        } else if let Some(x) = SYMBOLS.iter().find(|x| rest.starts_with(**x)) {
+            tokens.push((start, Token::Symbol(x)));
+            pos += x.len();
+        } else {
+            return Err(error(start, &format!("unexpected character {c}")));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    len: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|x| &x.1)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map(|x| x.0).unwrap_or(self.len)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, QueryError> {
+        Err(QueryError {
+            position: self.position(),
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|x| x.1.clone());
+        self.index += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(x)) if x.eq_ignore_ascii_case(keyword) => {
+                self.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.peek() {
+            Some(Token::Symbol(x)) if *x == symbol => {
+                self.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), QueryError> {
+        match self.symbol(symbol) {
+            true => Ok(()),
+            false => self.error(format!("expected {symbol}")),
+        }
+    }
+
+    /// runs f one level deeper
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, QueryError>,
+    ) -> Result<T, QueryError> {
+        if self.depth >= MAX_QUERY_DEPTH {
+            return self.error(format!("query nested deeper than {MAX_QUERY_DEPTH}"));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.and()?];
+        while self.keyword("or") {
+            terms.push(self.and()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Query::Or(terms),
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.unary()?];
+        while self.keyword("and") {
+            terms.push(self.unary()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Query::And(terms),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        if self.keyword("not") {
+            return self.nested(|x| Ok(Query::Not(Box::new(x.unary()?))));
+        }
+        if self.symbol("(") {
+            return self.nested(|x| {
+                let query = x.or()?;
+                x.expect_symbol(")")?;
+                Ok(query)
+            });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Query, QueryError> {
+        let field = self.field()?;
+        if self.keyword("in") {
+            self.expect_symbol("(")?;
+            let mut values = vec![self.value()?];
+            while self.symbol(",") {
+                values.push(self.value()?);
+            }
+            self.expect_symbol(")")?;
+            return Ok(Query::In { field, values });
+        }
+        let operator = match self.peek() {
+            Some(Token::Symbol("=")) => Operator::Equals,
+            Some(Token::Symbol("!=")) => Operator::NotEquals,
+            Some(Token::Symbol("<")) => Operator::Less,
+            Some(Token::Symbol("<=")) => Operator::LessOrEqual,
+            Some(Token::Symbol(">")) => Operator::Greater,
+            Some(Token::Symbol(">=")) => Operator::GreaterOrEqual,
+            Some(Token::Word(x)) if x.eq_ignore_ascii_case("contains") => Operator::Contains,
+            _ => return self.error("expected an operator"),
+        };
+        self.index += 1;
+        let value = self.value()?;
+        Ok(Query::Compare {
+            field,
+            operator,
+            value,
+        })
+    }
+
+    /// a label or annotation key, a word or quoted
+    fn key(&mut self) -> Result<String, QueryError> {
+        self.expect_symbol(".")?;
+        match self.next() {
+            Some(Token::Word(x)) | Some(Token::Quoted(x)) => Ok(x),
+            _ => {
+                self.index -= 1;
+                self.error("expected a key")
+            }
+        }
+    }
+
+    fn field(&mut self) -> Result<Field, QueryError> {
+        let position = self.position();
+        let field = match self.next() {
+            Some(Token::Ref(key_path)) => {
+                self.expect_symbol(".")?;
+                let field = Box::new(self.nested(Self::field)?);
+                return Ok(Field::Ref { key_path, field });
+            }
+            Some(Token::Word(x)) => x.to_ascii_lowercase(),
+            _ => {
+                self.index -= 1;
+                return self.error("expected a field");
+            }
+        };
+        Ok(match field.as_str() {
+            "namespace" => Field::Namespace,
+            "kind" => Field::Kind,
+            "name" => Field::Name,
+            "api_version" => Field::ApiVersion,
+            "labels" => Field::Label(self.key()?),
+            "annotations" => Field::Annotation(self.key()?),
+            "spec" => {
+                let mut path = Vec::new();
+                while self.symbol(".") {
+                    match self.next() {
+                        Some(Token::Word(x)) => path.push(x),
+                        // array indexes
+                        Some(Token::Number(x)) if x.is_u64() => path.push(x.to_string()),
+                        _ => {
+                            self.index -= 1;
+                            return self.error("expected a spec field");
+                        }
+                    }
+                }
+                if path.is_empty() {
+                    return self.error("expected a spec field");
+                }
+                Field::Spec(path)
+            }
+            x => {
+                return Err(QueryError {
+                    position,
+                    message: format!("unknown field {x}"),
+                });
+            }
+        })
+    }
+
+    fn value(&mut self) -> Result<Value, QueryError> {
+        Ok(match self.next() {
+            Some(Token::Quoted(x)) => Value::String(x),
+            Some(Token::Number(x)) => Value::Number(x),
+            Some(Token::Word(x)) => match x.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => Value::String(x),
+            },
+            _ => {
+                self.index -= 1;
+                return self.error("expected a value");
+            }
+        })
+    }
+}
+
+/// parses a query of the get endpoint
+pub fn parse_query(query: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        index: 0,
+        len: query.len(),
+        depth: 0,
+    };
+    let parsed = parser.or()?;
+    if parser.peek().is_some() {
+        return parser.error("unexpected token");
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn compare(field: Field, operator: Operator, value: Value) -> Query {
+        Query::Compare {
+            field,
+            operator,
+            value,
+        }
+    }
+
+    fn spec(path: &[&str]) -> Field {
+        Field::Spec(path.iter().map(|x| x.to_string()).collect())
+    }
+
+    fn error_at(query: &str) -> usize {
+        parse_query(query).unwrap_err().position
+    }
+
+    #[test]
+    fn tokenizes_numbers_and_paths() {
+        let tokens = tokenize("spec.a.0.1 >= -1.5e3")
+            .unwrap()
+            .into_iter()
+            .map(|x| x.1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("spec".into()),
+                Token::Symbol("."),
+                Token::Word("a".into()),
+                Token::Symbol("."),
+                Token::Number(0.into()),
+                Token::Symbol("."),
+                Token::Number(1.into()),
+                Token::Symbol(">="),
+                Token::Number(serde_json::Number::from_f64(-1500.0).unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_quoted_strings() {
+        let tokens = tokenize(r#""a \"b\" \\ c""#).unwrap();
+        assert_eq!(tokens, vec![(0, Token::Quoted(r#"a "b" \ c"#.into()))]);
+        assert_eq!(tokenize(r#"name = "open"#).unwrap_err().position, 7);
+    }
+
+    #[test]
+    fn tokenizes_ref_key_paths() {
+        let tokens = tokenize("ref( slots[*].item ).name").unwrap();
+        assert_eq!(tokens[0], (0, Token::Ref("slots[*].item".into())));
+        assert_eq!(tokens[1], (20, Token::Symbol(".")));
+        assert_eq!(tokenize("ref(parent.name").unwrap_err().position, 0);
+        assert_eq!(tokenize("ref().name").unwrap_err().position, 0);
+    }
+
+    #[test]
+    fn parses_spec_paths_with_indexes() {
+        assert_eq!(
+            parse_query("spec.a.0.1 = 2").unwrap(),
+            compare(spec(&["a", "0", "1"]), Operator::Equals, json!(2))
+        );
+        assert_eq!(
+            parse_query("spec.nr > 2.5").unwrap(),
+            compare(spec(&["nr"]), Operator::Greater, json!(2.5))
+        );
+    }
+
+    #[test]
+    fn parses_values() {
+        let values = parse_query(r#"name in (a, "b c", 1, true, null)"#).unwrap();
+        assert_eq!(
+            values,
+            Query::In {
+                field: Field::Name,
+                values: vec![json!("a"), json!("b c"), json!(1), json!(true), Value::Null],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_labels_and_quoted_keys() {
+        assert_eq!(
+            parse_query(r#"labels."app.io/name" != x"#).unwrap(),
+            compare(
+                Field::Label("app.io/name".into()),
+                Operator::NotEquals,
+                json!("x")
+            )
+        );
+    }
+
+    #[test]
+    fn parses_nested_refs() {
+        assert_eq!(
+            parse_query("ref(parent).ref(parent).labels.room = kitchen").unwrap(),
+            compare(
+                Field::Ref {
+                    key_path: "parent".into(),
+                    field: Box::new(Field::Ref {
+                        key_path: "parent".into(),
+                        field: Box::new(Field::Label("room".into())),
+                    }),
+                },
+                Operator::Equals,
+                json!("kitchen")
+            )
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let a = compare(Field::Name, Operator::Equals, json!("a"));
+        let b = compare(Field::Kind, Operator::Equals, json!("b"));
+        let c = compare(spec(&["c"]), Operator::Contains, json!("c"));
+        assert_eq!(
+            parse_query("name = a or kind = b AND spec.c contains c").unwrap(),
+            Query::Or(vec![a.clone(), Query::And(vec![b.clone(), c.clone()])])
+        );
+        assert_eq!(
+            parse_query("not (name = a or kind = b) and spec.c contains c").unwrap(),
+            Query::And(vec![Query::Not(Box::new(Query::Or(vec![a, b]))), c])
+        );
+    }
+
+    #[test]
+    fn long_chains_stay_flat() {
+        let query = vec!["name = a"; 1000].join(" and ");
+        let Query::And(terms) = parse_query(&query).unwrap() else {
+            panic!("expected and");
+        };
+        assert_eq!(terms.len(), 1000);
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let nested = |n: usize| format!("{}name = a{}", "(".repeat(n), ")".repeat(n));
+        assert!(parse_query(&nested(MAX_QUERY_DEPTH)).is_ok());
+        assert!(parse_query(&nested(MAX_QUERY_DEPTH + 1)).is_err());
+        assert!(parse_query(&nested(100_000)).is_err());
+        assert!(parse_query(&format!("{}name = a", "not ".repeat(100_000))).is_err());
+        assert!(parse_query(&format!("{}name = a", "ref(parent).".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(error_at("spec.nr > "), 10);
+        assert_eq!(error_at("name = a and foo = 1"), 13);
+        assert_eq!(error_at("name a"), 5);
+        assert_eq!(error_at("(name = a"), 9);
+        assert_eq!(error_at("name = a)"), 8);
+        assert_eq!(error_at("spec = 1"), 5);
+        assert_eq!(error_at("name = a $"), 9);
+    }
+}